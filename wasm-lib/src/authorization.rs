@@ -0,0 +1,79 @@
+use crate::context::wallet_context;
+use crate::error::WalletError;
+use crate::transfer::{handle_transaction, transfer_inputs, TransactionOutput};
+use crate::utils::parse_private_key;
+use anyhow::ensure;
+use snarkvm_console_program::{Network, Request};
+use snarkvm_synthesizer::{Authorization, Query, Transaction};
+
+/// Signs a `credits.aleo/transfer` with the private key, without proving it.
+/// Returns the serialized authorization, which can be proven on another machine.
+pub(crate) fn authorize_transfer_internal<N: Network>(
+    private_key: String,
+    record: String,
+    amount: u64,
+    recipient: String,
+) -> anyhow::Result<String> {
+    let inputs = transfer_inputs::<N>(&record, amount, &recipient)?;
+
+    // Retrieve the private key.
+    let private_key = parse_private_key::<N>(&private_key)?;
+    // Authorize the transfer.
+    let authorization = {
+        // Initialize an RNG.
+        let rng = &mut rand::thread_rng();
+
+        // Retrieve the VM from the wallet context.
+        let context = wallet_context::<N>()?;
+        context
+            .vm()
+            .authorize(&private_key, "credits.aleo", "transfer", inputs.iter(), rng)
+            .map_err(|e| WalletError::Proving(e.to_string()))?
+    };
+
+    serialize_authorization(&authorization)
+}
+
+/// Proves the serialized authorization into a transaction, without needing the private key.
+pub(crate) async fn prove_authorization_internal<N: Network>(
+    authorization: String,
+    query_endpoint: String,
+) -> anyhow::Result<TransactionOutput> {
+    let authorization = deserialize_authorization::<N>(&authorization)?;
+    let operation = match authorization.peek_next() {
+        Ok(request) => format!("{}/{}", request.program_id(), request.function_name()),
+        Err(_) => String::new(),
+    };
+
+    // Specify the query
+    let query = Query::from(&query_endpoint);
+
+    // Generate the execution transaction.
+    let execution = {
+        // Initialize an RNG.
+        let rng = &mut rand::thread_rng();
+
+        // Retrieve the VM from the wallet context.
+        let context = wallet_context::<N>()?;
+        Transaction::execute_authorization(context.vm(), authorization, Some(query), rng)
+            .map_err(|e| WalletError::Proving(e.to_string()))?
+    };
+    // Return the transaction to the caller, so that it can be broadcast separately.
+    handle_transaction(None, true, true, execution, operation, None).await
+}
+
+/// Serializes the authorization as a JSON array of its requests.
+fn serialize_authorization<N: Network>(authorization: &Authorization<N>) -> anyhow::Result<String> {
+    let requests = authorization.to_vec_deque();
+    Ok(serde_json::to_string(&requests)?)
+}
+
+fn deserialize_authorization<N: Network>(authorization: &str) -> anyhow::Result<Authorization<N>> {
+    let requests: Vec<Request<N>> = serde_json::from_str(authorization)
+        .map_err(|_| WalletError::Parse("Failed to parse the authorization".to_string()))?;
+    ensure!(
+        !requests.is_empty(),
+        WalletError::Parse("The authorization has no requests".to_string())
+    );
+    Ok(Authorization::new(&requests))
+}
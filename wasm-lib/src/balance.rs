@@ -0,0 +1,209 @@
+use crate::error::WalletError;
+use crate::mapping::fetch_mapping_value;
+use crate::records::{request_records_internal, BlockFetch, RecordStatus, SpentCheck};
+use crate::sync::{sync_internal, ScanState};
+use crate::utils::{parse_record, record_microcredits};
+use anyhow::ensure;
+use snarkvm_console_account::{Address, ViewKey};
+use snarkvm_console_program::{Identifier, Literal, Network, Plaintext, ProgramID, Value};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// The balance of an account.
+#[wasm_bindgen]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Balance {
+    total: u64,
+    spendable: u64,
+    unknown: u64,
+    record_count: u32,
+    largest_record: u64,
+    public_balance: Option<u64>,
+    state: Option<String>,
+}
+
+#[wasm_bindgen]
+impl Balance {
+    /// The microcredits held by all records which are not known to be spent.
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The microcredits held by records which are known to be unspent.
+    #[wasm_bindgen(getter)]
+    pub fn spendable(&self) -> u64 {
+        self.spendable
+    }
+
+    /// The microcredits held by records whose spent status could not be checked.
+    #[wasm_bindgen(getter)]
+    pub fn unknown(&self) -> u64 {
+        self.unknown
+    }
+
+    /// The number of records which are not known to be spent.
+    #[wasm_bindgen(getter)]
+    pub fn record_count(&self) -> u32 {
+        self.record_count
+    }
+
+    /// The microcredits held by the largest record which is not known to be spent.
+    #[wasm_bindgen(getter)]
+    pub fn largest_record(&self) -> u64 {
+        self.largest_record
+    }
+
+    /// The public balance in the `credits.aleo` `account` mapping, if the endpoint provided it.
+    #[wasm_bindgen(getter)]
+    pub fn public_balance(&self) -> Option<u64> {
+        self.public_balance
+    }
+
+    /// The updated scan state, if the balance was computed from one.
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> Option<String> {
+        self.state.clone()
+    }
+}
+
+/// Computes the balance of the account, either from the records in the given block range or,
+/// if a scan state is given, from the records in the state once it has been synced.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn get_balance_internal<N: Network>(
+    private_key: Option<String>,
+    view_key: String,
+    start: Option<u32>,
+    end: Option<u32>,
+    last: Option<u32>,
+    state: Option<String>,
+    endpoint: String,
+    spent_check: SpentCheck,
+) -> anyhow::Result<Balance> {
+    // A state is synced up to the latest block, so it cannot be limited to a range.
+    ensure!(
+        state.is_none() || (start.is_none() && end.is_none() && last.is_none()),
+        WalletError::InvalidInput(
+            "A scan state can't be used with `start`, `end` or `last`".to_string()
+        )
+    );
+
+    let address = ViewKey::<N>::from_str(&view_key)
+        .map_err(|e| WalletError::InvalidKey(format!("view key: {e}")))?
+        .to_address();
+
+    let mut balance = match state {
+        Some(state) => {
+            let output = sync_internal::<N>(
+                private_key,
+                view_key,
+                Some(state),
+                endpoint.clone(),
+                spent_check,
+                BlockFetch::default(),
+            )
+            .await?;
+            let state = ScanState::from_json(&output.state())?;
+
+            let mut records = Vec::new();
+            for record in &state.records {
+                let microcredits = record_microcredits(&parse_record::<N>(&record.record)?);
+                records.push((microcredits, record.status));
+            }
+            let mut balance = summarize(records);
+            balance.state = Some(output.state());
+            balance
+        }
+        None => {
+            let records = request_records_internal::<N>(
+                private_key,
+                view_key,
+                start,
+                end,
+                last,
+                endpoint.clone(),
+                spent_check,
+                BlockFetch::default(),
+                None,
+                None,
+            )
+            .await?;
+            summarize(
+                records
+                    .iter()
+                    .map(|record| (record.microcredits(), record.record_status())),
+            )
+        }
+    };
+
+    // The public balance is optional, so a failed lookup only leaves it out.
+    balance.public_balance = fetch_public_balance::<N>(&endpoint, &address)
+        .await
+        .ok()
+        .flatten();
+    Ok(balance)
+}
+
+/// Sums up the microcredits of the given records by spent status. Spent records are ignored.
+fn summarize(records: impl IntoIterator<Item = (u64, RecordStatus)>) -> Balance {
+    let mut balance = Balance::default();
+    for (microcredits, status) in records {
+        match status {
+            RecordStatus::Spent => continue,
+            RecordStatus::Unspent => {
+                balance.spendable = balance.spendable.saturating_add(microcredits)
+            }
+            RecordStatus::Unknown => balance.unknown = balance.unknown.saturating_add(microcredits),
+        }
+        balance.total = balance.total.saturating_add(microcredits);
+        balance.record_count += 1;
+        balance.largest_record = balance.largest_record.max(microcredits);
+    }
+    balance
+}
+
+/// Fetches the public balance of the address from the `credits.aleo` `account` mapping.
+/// Returns `None` if the address has no entry in the mapping.
+async fn fetch_public_balance<N: Network>(
+    endpoint: &str,
+    address: &Address<N>,
+) -> anyhow::Result<Option<u64>> {
+    let value = fetch_mapping_value(
+        endpoint,
+        &ProgramID::from_str("credits.aleo")?,
+        &Identifier::from_str("account")?,
+        &Plaintext::from(Literal::Address(*address)),
+    )
+    .await?;
+    match value {
+        None => Ok(None),
+        Some(Value::Plaintext(Plaintext::Literal(Literal::U64(amount), _))) => Ok(Some(*amount)),
+        Some(value) => {
+            Err(WalletError::Parse(format!("Unexpected public balance: {value}")).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{summarize, Balance};
+    use crate::records::RecordStatus;
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(Vec::new()), Balance::default());
+
+        let balance = summarize(vec![
+            (100, RecordStatus::Unspent),
+            (250, RecordStatus::Unknown),
+            (1000, RecordStatus::Spent),
+            (50, RecordStatus::Unspent),
+        ]);
+        assert_eq!(balance.total(), 400);
+        assert_eq!(balance.spendable(), 150);
+        assert_eq!(balance.unknown(), 250);
+        assert_eq!(balance.record_count(), 3);
+        assert_eq!(balance.largest_record(), 250);
+        assert_eq!(balance.public_balance(), None);
+    }
+}
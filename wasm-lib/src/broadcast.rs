@@ -0,0 +1,163 @@
+use crate::context::wallet_context;
+use crate::error::WalletError;
+use crate::program::{load_program, load_verifying_keys};
+use crate::transfer::{handle_transaction, TransactionOutput};
+use anyhow::ensure;
+use snarkvm_console_program::{Network, ProgramID};
+use snarkvm_synthesizer::{ConsensusMemory, Transaction, VM};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// Broadcasts a transaction built elsewhere (e.g. by the Aleo SDK or CLI) to the endpoint,
+/// returning the transaction id confirmed by the node.
+pub(crate) async fn broadcast_transaction_internal<N: Network>(
+    transaction: String,
+    endpoint: String,
+) -> anyhow::Result<TransactionOutput> {
+    let transaction = Transaction::<N>::from_str(&transaction)
+        .map_err(|e| WalletError::Parse(format!("transaction: {e}")))?;
+    validate_transaction(&transaction)?;
+
+    let operation = match &transaction {
+        Transaction::Deploy(_, deployment, _) => deployment.program_id().to_string(),
+        Transaction::Execute(_, execution, _) => match execution.peek() {
+            Ok(transition) => format!("{}/{}", transition.program_id(), transition.function_name()),
+            Err(_) => transaction.id().to_string(),
+        },
+    };
+    handle_transaction(Some(endpoint), false, false, transaction, operation, None).await
+}
+
+/// Verifies a transaction received from a third party, failing with the reason if it is invalid.
+/// The programs it executes and their verifying keys, or the imports of the program it deploys,
+/// are fetched from the query endpoint when they are not loaded yet.
+pub(crate) async fn verify_transaction_internal<N: Network>(
+    transaction: String,
+    query_endpoint: Option<String>,
+) -> anyhow::Result<()> {
+    let transaction = Transaction::<N>::from_str(&transaction)
+        .map_err(|e| WalletError::Parse(format!("transaction: {e}")))?;
+
+    let context = wallet_context::<N>()?;
+    if let Some(endpoint) = query_endpoint {
+        let program_ids: Vec<ProgramID<N>> = match &transaction {
+            Transaction::Deploy(_, deployment, _) => {
+                deployment.program().imports().keys().copied().collect()
+            }
+            Transaction::Execute(_, execution, _) => execution
+                .transitions()
+                .map(|transition| *transition.program_id())
+                .collect(),
+        };
+        for program_id in &program_ids {
+            load_program(context.vm(), &endpoint, program_id).await?;
+        }
+
+        if let Transaction::Execute(_, execution, _) = &transaction {
+            let functions = execution
+                .transitions()
+                .map(|transition| (*transition.program_id(), *transition.function_name()))
+                .collect::<Vec<_>>();
+            load_verifying_keys(context.vm(), &endpoint, &functions).await?;
+        }
+    }
+
+    ensure_verifiable(context.vm(), &transaction)?;
+    ensure_verified(context.vm(), &transaction)
+}
+
+/// Ensures that everything needed to verify the transaction is loaded, so that a missing program
+/// or verifying key is reported as such rather than as an invalid transaction.
+fn ensure_verifiable<N: Network>(
+    vm: &VM<N, ConsensusMemory<N>>,
+    transaction: &Transaction<N>,
+) -> anyhow::Result<()> {
+    let process = vm.process();
+    let process = process.read();
+    match transaction {
+        Transaction::Deploy(_, deployment, _) => {
+            for import in deployment.program().imports().keys() {
+                ensure!(
+                    process.contains_program(import),
+                    WalletError::Verification(format!(
+                        "Cannot verify the transaction: the program '{import}' is not loaded"
+                    ))
+                );
+            }
+        }
+        Transaction::Execute(_, execution, _) => {
+            for transition in execution.transitions() {
+                let (program_id, function_name) =
+                    (transition.program_id(), transition.function_name());
+                ensure!(
+                    process.get_verifying_key(program_id, function_name).is_ok(),
+                    WalletError::Verification(format!(
+                        "Cannot verify the transaction: the verifying key of \
+                         '{program_id}/{function_name}' is not loaded"
+                    ))
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the structure of the transaction and verifies its proofs with the loaded verifying keys.
+pub(crate) fn ensure_verified<N: Network>(
+    vm: &VM<N, ConsensusMemory<N>>,
+    transaction: &Transaction<N>,
+) -> anyhow::Result<()> {
+    validate_transaction(transaction)?;
+    ensure!(
+        vm.verify_transaction(transaction),
+        WalletError::Verification(format!(
+            "The transaction {} failed verification",
+            transaction.id()
+        ))
+    );
+    Ok(())
+}
+
+/// Checks the structure of the transaction, so that malformed transactions never reach the node.
+pub(crate) fn validate_transaction<N: Network>(transaction: &Transaction<N>) -> anyhow::Result<()> {
+    // Recompute the transaction id from its contents.
+    let expected = match transaction {
+        Transaction::Deploy(_, deployment, fee) => {
+            let program = deployment.program();
+            ensure!(
+                program.functions().len() == deployment.verifying_keys().len(),
+                WalletError::Verification(format!(
+                    "The deployment of '{}' does not have a verifying key for every function",
+                    program.id()
+                ))
+            );
+            Transaction::from_deployment(deployment.clone(), fee.clone())
+        }
+        Transaction::Execute(_, execution, fee) => {
+            ensure!(
+                !execution.is_empty(),
+                WalletError::Verification("The execution has no transitions".to_string())
+            );
+            let mut transition_ids = HashSet::new();
+            ensure!(
+                execution
+                    .transitions()
+                    .all(|transition| transition_ids.insert(*transition.id())),
+                WalletError::Verification(
+                    "The execution contains duplicate transitions".to_string()
+                )
+            );
+            Transaction::from_execution(execution.clone(), fee.clone())
+        }
+    }
+    .map_err(|e| WalletError::Verification(e.to_string()))?;
+    ensure!(
+        expected.id() == transaction.id(),
+        WalletError::Verification(format!(
+            "The transaction id ({}) does not match its contents ({})",
+            transaction.id(),
+            expected.id()
+        ))
+    );
+    Ok(())
+}
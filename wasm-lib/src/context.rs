@@ -0,0 +1,102 @@
+use crate::error::to_js_error;
+use crate::utils::load_credits_proving_keys;
+use crate::CurrentNetwork;
+use snarkvm_console_program::{Identifier, Network, ProgramID};
+use snarkvm_synthesizer::{ConsensusMemory, ConsensusStore, ProvingKey, VM};
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    // The wallet context shared by every call into the wasm module.
+    static WALLET_CONTEXT: RefCell<Option<Rc<dyn Any>>> = RefCell::new(None);
+    // Whether transactions are verified locally before they are broadcast.
+    static VERIFY_TRANSACTIONS: Cell<bool> = Cell::new(false);
+}
+
+/// The state kept alive between wasm calls: the VM, whose process holds the loaded
+/// proving/verifying keys and every program imported so far.
+pub(crate) struct Context<N: Network> {
+    vm: VM<N, ConsensusMemory<N>>,
+}
+
+impl<N: Network> Context<N> {
+    fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            vm: initialize_vm::<N>()?,
+        })
+    }
+
+    pub(crate) fn vm(&self) -> &VM<N, ConsensusMemory<N>> {
+        &self.vm
+    }
+}
+
+/// Returns the wallet context, initializing it on first use.
+pub(crate) fn wallet_context<N: Network>() -> anyhow::Result<Rc<Context<N>>> {
+    WALLET_CONTEXT.with(|context| {
+        if let Some(existing) = context.borrow().as_ref() {
+            if let Ok(existing) = existing.clone().downcast::<Context<N>>() {
+                return Ok(existing);
+            }
+        }
+
+        let new_context = Rc::new(Context::<N>::new()?);
+        *context.borrow_mut() = Some(new_context.clone());
+        Ok(new_context)
+    })
+}
+
+/// Returns `true` if transactions should be verified before they are broadcast.
+pub(crate) fn verify_transactions() -> bool {
+    VERIFY_TRANSACTIONS.with(|verify| verify.get())
+}
+
+/// Initializes a VM whose process is preloaded with the bundled `credits.aleo` proving keys,
+/// so that executing a credits function never has to synthesize or download them.
+fn initialize_vm<N: Network>() -> anyhow::Result<VM<N, ConsensusMemory<N>>> {
+    let store = ConsensusStore::<N, ConsensusMemory<N>>::open(None)?;
+    let vm = VM::from(store)?;
+
+    let program_id = ProgramID::<N>::from_str("credits.aleo")?;
+    for (function_name, proving_key) in load_credits_proving_keys::<N>()?.iter() {
+        vm.process().read().insert_proving_key(
+            &program_id,
+            &Identifier::from_str(function_name)?,
+            ProvingKey::new(proving_key.clone()),
+        )?;
+    }
+    Ok(vm)
+}
+
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct WalletContext;
+
+#[wasm_bindgen]
+impl WalletContext {
+    /// Eagerly initializes the wallet context, so that the first transaction does not pay for it.
+    pub fn initialize() -> Result<(), JsValue> {
+        wallet_context::<CurrentNetwork>()
+            .map(|_| ())
+            .map_err(to_js_error)
+    }
+
+    #[wasm_bindgen(js_name = isInitialized)]
+    pub fn is_initialized() -> bool {
+        WALLET_CONTEXT.with(|context| context.borrow().is_some())
+    }
+
+    /// Enables or disables verifying every transaction inside wasm before it is broadcast.
+    #[wasm_bindgen(js_name = setVerifyTransactions)]
+    pub fn set_verify_transactions(verify: bool) {
+        VERIFY_TRANSACTIONS.with(|verify_transactions| verify_transactions.set(verify))
+    }
+
+    /// Drops the wallet context; the next call will initialize a fresh one.
+    pub fn reset() {
+        WALLET_CONTEXT.with(|context| *context.borrow_mut() = None)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::context::wallet_context;
+use crate::error::WalletError;
+use crate::program::load_program;
+use crate::transfer::{handle_transaction, prepare_fee, TransactionOutput};
+use crate::utils::parse_private_key;
+use anyhow::ensure;
+use snarkvm_console_program::Network;
+use snarkvm_synthesizer::{Program, Query, Transaction};
+use std::str::FromStr;
+
+pub(crate) async fn deploy_internal<N: Network>(
+    private_key: String,
+    program_source: String,
+    fee_record: String,
+    fee: u64,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    let program = Program::<N>::from_str(&program_source)
+        .map_err(|e| WalletError::Parse(format!("program: {e}")))?;
+
+    // Prepare the fees.
+    let fee = prepare_fee::<N>(Some(fee_record), Some(fee))?
+        .ok_or_else(|| WalletError::InvalidInput("A deployment requires a fee".to_string()))?;
+
+    // Add the imports of the program to the VM.
+    let context = wallet_context::<N>()?;
+    let vm = context.vm();
+    ensure!(
+        !vm.process().read().contains_program(program.id()),
+        WalletError::InvalidInput(format!("The program '{}' already exists", program.id()))
+    );
+    for import in program.imports().keys() {
+        load_program(vm, &query_endpoint, import).await?;
+    }
+
+    // Specify the query
+    let query = Query::from(&query_endpoint);
+
+    // Retrieve the private key.
+    let private_key = parse_private_key::<N>(&private_key)?;
+    // Generate the deployment transaction, synthesizing the program keys along the way.
+    let deployment = {
+        // Initialize an RNG.
+        let rng = &mut rand::thread_rng();
+
+        // Create a new transaction.
+        Transaction::deploy(vm, &private_key, &program, fee, Some(query), rng)
+            .map_err(|e| WalletError::Proving(e.to_string()))?
+    };
+    // Broadcast the transaction, or return it to the caller when no endpoint is given.
+    let build = broadcast.is_none();
+    handle_transaction(
+        broadcast,
+        build,
+        build,
+        deployment,
+        program.id().to_string(),
+        None,
+    )
+    .await
+}
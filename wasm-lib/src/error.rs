@@ -0,0 +1,153 @@
+use js_sys::{Object, Reflect};
+use std::fmt;
+use wasm_bindgen::JsValue;
+
+/// The errors surfaced to JS. Each one is thrown as an `Error` object carrying a `code`
+/// and a `details` object, so that callers never have to string-match messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WalletError {
+    /// A private key, view key or address could not be parsed.
+    InvalidKey(String),
+    /// A record could not be parsed, or it cannot cover the requested amount.
+    InvalidRecord(String),
+    /// An argument is malformed or inconsistent with the others.
+    InvalidInput(String),
+    /// A request failed, or the endpoint answered with a non-success HTTP status.
+    Network {
+        status: Option<u16>,
+        message: String,
+    },
+    /// A response, program, value or transaction could not be parsed.
+    Parse(String),
+    /// Authorizing or proving a transaction failed.
+    Proving(String),
+    /// A transaction is malformed or its proofs do not verify.
+    Verification(String),
+    /// The endpoint accepted the transaction under a different id.
+    BroadcastMismatch { expected: String, received: String },
+    /// The caller aborted the operation through its `AbortSignal`.
+    Aborted,
+    /// Anything else.
+    Internal(String),
+}
+
+impl WalletError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            WalletError::InvalidKey(..) => "INVALID_KEY",
+            WalletError::InvalidRecord(..) => "INVALID_RECORD",
+            WalletError::InvalidInput(..) => "INVALID_INPUT",
+            WalletError::Network { .. } => "NETWORK",
+            WalletError::Parse(..) => "PARSE",
+            WalletError::Proving(..) => "PROVING",
+            WalletError::Verification(..) => "VERIFICATION",
+            WalletError::BroadcastMismatch { .. } => "BROADCAST_MISMATCH",
+            WalletError::Aborted => "ABORTED",
+            WalletError::Internal(..) => "INTERNAL",
+        }
+    }
+
+    /// Returns the `details` object attached to the thrown JS error.
+    fn details(&self) -> Object {
+        let details = Object::new();
+        let set = |key: &str, value: JsValue| {
+            let _ = Reflect::set(&details, &JsValue::from_str(key), &value);
+        };
+        match self {
+            WalletError::InvalidKey(message)
+            | WalletError::InvalidRecord(message)
+            | WalletError::InvalidInput(message)
+            | WalletError::Parse(message)
+            | WalletError::Proving(message)
+            | WalletError::Verification(message)
+            | WalletError::Internal(message) => set("message", JsValue::from_str(message)),
+            WalletError::Network { status, message } => {
+                set("status", status.map(JsValue::from).unwrap_or(JsValue::NULL));
+                set("message", JsValue::from_str(message));
+            }
+            WalletError::BroadcastMismatch { expected, received } => {
+                set("expected", JsValue::from_str(expected));
+                set("received", JsValue::from_str(received));
+            }
+            WalletError::Aborted => {}
+        }
+        details
+    }
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletError::InvalidKey(message) => write!(f, "Invalid key: {message}"),
+            WalletError::InvalidRecord(message) => write!(f, "Invalid record: {message}"),
+            WalletError::InvalidInput(message) => write!(f, "Invalid input: {message}"),
+            WalletError::Network {
+                status: Some(status),
+                message,
+            } => write!(f, "Network error (status {status}): {message}"),
+            WalletError::Network {
+                status: None,
+                message,
+            } => write!(f, "Network error: {message}"),
+            WalletError::Parse(message) => write!(f, "Parse error: {message}"),
+            WalletError::Proving(message) => write!(f, "Proving error: {message}"),
+            WalletError::Verification(message) => write!(f, "Verification error: {message}"),
+            WalletError::BroadcastMismatch { expected, received } => write!(
+                f,
+                "The response ({received}) does not match the transaction id ({expected})"
+            ),
+            WalletError::Aborted => write!(f, "The operation was aborted"),
+            WalletError::Internal(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for WalletError {}
+
+impl From<anyhow::Error> for WalletError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<WalletError>() {
+            Ok(error) => error,
+            Err(error) => WalletError::Internal(error.to_string()),
+        }
+    }
+}
+
+impl From<WalletError> for JsValue {
+    fn from(error: WalletError) -> Self {
+        let js_error = js_sys::Error::new(&error.to_string());
+        let _ = Reflect::set(&js_error, &JsValue::from_str("code"), &error.code().into());
+        let _ = Reflect::set(&js_error, &JsValue::from_str("details"), &error.details());
+        js_error.into()
+    }
+}
+
+/// Converts an error raised inside the crate into the JS error thrown to the caller.
+pub(crate) fn to_js_error(error: anyhow::Error) -> JsValue {
+    WalletError::from(error).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WalletError;
+
+    #[test]
+    fn test_wallet_error_from_anyhow() {
+        let error = anyhow::Error::new(WalletError::Network {
+            status: Some(404),
+            message: "not found".to_string(),
+        });
+        let error = WalletError::from(error.context("Failed to fetch"));
+        assert_eq!(error.code(), "NETWORK");
+        assert_eq!(
+            error,
+            WalletError::Network {
+                status: Some(404),
+                message: "not found".to_string()
+            }
+        );
+
+        let error = WalletError::from(anyhow::Error::msg("unexpected"));
+        assert_eq!(error, WalletError::Internal("unexpected".to_string()));
+    }
+}
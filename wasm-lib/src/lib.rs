@@ -1,11 +1,44 @@
+mod authorization;
+mod balance;
+mod broadcast;
+mod context;
+mod deploy;
+mod error;
+mod mapping;
+mod owned_record;
+mod program;
+mod range;
 mod records;
+mod send;
+mod sync;
 mod transfer;
 mod utils;
 
-use crate::records::{request_records_internal, RecordScanner};
-use crate::transfer::transfer_internal;
+use crate::authorization::{authorize_transfer_internal, prove_authorization_internal};
+use crate::balance::get_balance_internal;
+pub use crate::balance::Balance;
+use crate::broadcast::{broadcast_transaction_internal, verify_transaction_internal};
+pub use crate::context::WalletContext;
+use crate::deploy::deploy_internal;
+use crate::error::to_js_error;
+pub use crate::error::WalletError;
+use crate::mapping::{get_mapping_value_internal, list_mappings_internal};
+pub use crate::owned_record::OwnedRecord;
+pub use crate::records::SpentCheck;
+use crate::records::{request_records_internal, BlockFetch, RecordScanner};
+use crate::send::{send_internal, spendable_records};
+pub use crate::send::{SelectionStrategy, SendOutput};
+use crate::sync::sync_internal;
+pub use crate::sync::SyncOutput;
+pub use crate::transfer::TransactionOutput;
+use crate::transfer::{
+    execute_program_internal, fee_internal, join_internal, mint_internal, split_internal,
+    transfer_internal,
+};
+use js_sys::{Array, Function};
 use snarkvm_console_network::Testnet3;
 use wasm_bindgen::prelude::*;
+use web_sys::AbortSignal;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
@@ -25,6 +58,16 @@ type CurrentNetwork = Testnet3;
 //     alert("Hello, aleo-wallet-test!");
 // }
 
+/// Scans the given block range for records owned by the view key. `start` is inclusive and `end`
+/// exclusive; without an `end` the scan runs up to the latest block, and `last` scans the latest
+/// `last` blocks. The optional `callback` is
+/// called with `{scanned, total, found}` after every batch of blocks, and with
+/// `{scanned, total, found, record}` for every record as soon as it is found. Aborting the
+/// optional `signal` stops the scan between batches and cancels the requests in flight.
+///
+/// Blocks are requested `window_size` at a time (50 by default), with up to `concurrency`
+/// windows in flight at once (4 by default).
+#[allow(clippy::too_many_arguments)]
 #[wasm_bindgen]
 pub async fn request_records(
     private_key: Option<String>,
@@ -33,45 +76,352 @@ pub async fn request_records(
     end: Option<u32>,
     last: Option<u32>,
     endpoint: String,
-) -> RecordScanner {
-    match request_records_internal::<CurrentNetwork>(
+    spent_check: Option<SpentCheck>,
+    window_size: Option<u32>,
+    concurrency: Option<usize>,
+    callback: Option<Function>,
+    signal: Option<AbortSignal>,
+) -> Result<RecordScanner, JsValue> {
+    let records = request_records_internal::<CurrentNetwork>(
         private_key,
         view_key,
         start,
         end,
         last,
         endpoint,
+        spent_check.unwrap_or_default(),
+        BlockFetch::new(window_size, concurrency).map_err(to_js_error)?,
+        callback,
+        signal,
     )
     .await
-    {
-        Ok(records) => RecordScanner::new(
-            "".to_string(),
-            records.into_iter().map(|r| JsValue::from_str(&r)).collect(),
-        ),
-        Err(e) => RecordScanner::new(e.to_string(), Default::default()),
-    }
+    .map_err(to_js_error)?;
+    Ok(RecordScanner::new(
+        records.into_iter().map(JsValue::from).collect(),
+    ))
 }
 
+/// Scans the blocks produced since the given scan state, and returns the updated state as JSON
+/// along with the depth of any reorg that was rolled back. Without a state, the whole chain is scanned.
+/// `window_size` and `concurrency` control the block requests, as for `request_records`.
+#[wasm_bindgen]
+pub async fn sync(
+    private_key: Option<String>,
+    view_key: String,
+    state: Option<String>,
+    endpoint: String,
+    spent_check: Option<SpentCheck>,
+    window_size: Option<u32>,
+    concurrency: Option<usize>,
+) -> Result<SyncOutput, JsValue> {
+    sync_internal::<CurrentNetwork>(
+        private_key,
+        view_key,
+        state,
+        endpoint,
+        spent_check.unwrap_or_default(),
+        BlockFetch::new(window_size, concurrency).map_err(to_js_error)?,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+/// Computes the balance of the account from the records in the given block range, or from the
+/// records in the given scan state once it has been synced. A state can't be combined with a block
+/// range. The public balance is looked up in the `credits.aleo` `account` mapping.
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub async fn get_balance(
+    private_key: Option<String>,
+    view_key: String,
+    start: Option<u32>,
+    end: Option<u32>,
+    last: Option<u32>,
+    state: Option<String>,
+    endpoint: String,
+    spent_check: Option<SpentCheck>,
+) -> Result<Balance, JsValue> {
+    get_balance_internal::<CurrentNetwork>(
+        private_key,
+        view_key,
+        start,
+        end,
+        last,
+        state,
+        endpoint,
+        spent_check.unwrap_or_default(),
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+/// Reads the value stored under `key` in a mapping of the given program, e.g. the public balance
+/// of an address in the `credits.aleo` `account` mapping. Returns `undefined` for a missing key.
+#[wasm_bindgen]
+pub async fn get_mapping_value(
+    program_id: String,
+    mapping: String,
+    key: String,
+    endpoint: String,
+) -> Result<Option<String>, JsValue> {
+    get_mapping_value_internal::<CurrentNetwork>(program_id, mapping, key, endpoint)
+        .await
+        .map_err(to_js_error)
+}
+
+/// Lists the names of the mappings declared by the given program.
+#[wasm_bindgen]
+pub async fn list_mappings(program_id: String, endpoint: String) -> Result<Array, JsValue> {
+    let mappings = list_mappings_internal::<CurrentNetwork>(program_id, endpoint)
+        .await
+        .map_err(to_js_error)?;
+    Ok(mappings
+        .iter()
+        .map(|mapping| JsValue::from_str(mapping))
+        .collect())
+}
+
+/// Proves a transfer and broadcasts it, if a broadcast endpoint is given. Aborting the optional
+/// `signal` cancels the transfer before proving starts, or the broadcast request in flight.
+#[allow(clippy::too_many_arguments)]
 #[wasm_bindgen]
 pub async fn transfer(
     private_key: String,
     record: String,
     amount: u64,
     recipient: String,
+    fee_record: Option<String>,
+    fee: Option<u64>,
     query_endpoint: String,
-    broadcast: String,
-) -> String {
-    match transfer_internal::<CurrentNetwork>(
+    broadcast: Option<String>,
+    signal: Option<AbortSignal>,
+) -> Result<TransactionOutput, JsValue> {
+    transfer_internal::<CurrentNetwork>(
         private_key,
         record,
         amount,
         recipient,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        signal,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+/// Sends the amount to the recipient, picking the records to spend from the given record
+/// plaintexts or unspent `OwnedRecord`s according to `strategy`, and a separate record to pay
+/// the fee. If no single record covers the amount, the picked records are joined first; the
+/// joins are broadcast and must be confirmed before the transfer, so a broadcast endpoint is
+/// required in that case. Each join pays the fee as well, out of the same fee record.
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub async fn send(
+    private_key: String,
+    records: Array,
+    amount: u64,
+    recipient: String,
+    fee: Option<u64>,
+    strategy: Option<SelectionStrategy>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+    signal: Option<AbortSignal>,
+) -> Result<SendOutput, JsValue> {
+    let records = spendable_records(&records).map_err(to_js_error)?;
+    send_internal::<CurrentNetwork>(
+        private_key,
+        records,
+        amount,
+        recipient,
+        fee,
+        strategy.unwrap_or_default(),
+        query_endpoint,
+        broadcast,
+        signal,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn split(
+    private_key: String,
+    record: String,
+    amount: u64,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> Result<TransactionOutput, JsValue> {
+    split_internal::<CurrentNetwork>(
+        private_key,
+        record,
+        amount,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn join(
+    private_key: String,
+    first_record: String,
+    second_record: String,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> Result<TransactionOutput, JsValue> {
+    join_internal::<CurrentNetwork>(
+        private_key,
+        first_record,
+        second_record,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn mint(
+    private_key: String,
+    recipient: String,
+    amount: u64,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> Result<TransactionOutput, JsValue> {
+    mint_internal::<CurrentNetwork>(
+        private_key,
+        recipient,
+        amount,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn fee(
+    private_key: String,
+    record: String,
+    amount: u64,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> Result<TransactionOutput, JsValue> {
+    fee_internal::<CurrentNetwork>(private_key, record, amount, query_endpoint, broadcast)
+        .await
+        .map_err(to_js_error)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub async fn execute_program(
+    private_key: String,
+    program_id: String,
+    function: String,
+    inputs: Array,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> Result<TransactionOutput, JsValue> {
+    let inputs = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, input)| {
+            input
+                .as_string()
+                .ok_or_else(|| WalletError::InvalidInput(format!("input {i} must be a string")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    execute_program_internal::<CurrentNetwork>(
+        private_key,
+        program_id,
+        function,
+        inputs,
+        fee_record,
+        fee,
         query_endpoint,
         broadcast,
     )
     .await
-    {
-        Ok(transaction) => transaction,
-        Err(e) => format!("error: {}", e),
-    }
+    .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn deploy(
+    private_key: String,
+    program_source: String,
+    fee_record: String,
+    fee: u64,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> Result<TransactionOutput, JsValue> {
+    deploy_internal::<CurrentNetwork>(
+        private_key,
+        program_source,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+    )
+    .await
+    .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub fn authorize_transfer(
+    private_key: String,
+    record: String,
+    amount: u64,
+    recipient: String,
+) -> Result<String, JsValue> {
+    authorize_transfer_internal::<CurrentNetwork>(private_key, record, amount, recipient)
+        .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn prove_authorization(
+    authorization: String,
+    query_endpoint: String,
+) -> Result<TransactionOutput, JsValue> {
+    prove_authorization_internal::<CurrentNetwork>(authorization, query_endpoint)
+        .await
+        .map_err(to_js_error)
+}
+
+#[wasm_bindgen]
+pub async fn broadcast_transaction(
+    transaction: String,
+    endpoint: String,
+) -> Result<TransactionOutput, JsValue> {
+    broadcast_transaction_internal::<CurrentNetwork>(transaction, endpoint)
+        .await
+        .map_err(to_js_error)
+}
+
+/// Resolves if the transaction is valid, and rejects with the reason otherwise. Without a query
+/// endpoint, only the programs and verifying keys which are already loaded are used.
+#[wasm_bindgen]
+pub async fn verify_transaction(
+    transaction: String,
+    query_endpoint: Option<String>,
+) -> Result<(), JsValue> {
+    verify_transaction_internal::<CurrentNetwork>(transaction, query_endpoint)
+        .await
+        .map_err(to_js_error)
 }
@@ -0,0 +1,94 @@
+use crate::error::WalletError;
+use crate::utils::{get_request, response_text};
+use anyhow::ensure;
+use js_sys::encode_uri_component;
+use snarkvm_console_program::{Identifier, Network, Plaintext, ProgramID, Value};
+use std::str::FromStr;
+use web_sys::Response;
+
+/// Reads the value stored under `key` in a mapping of the given program, returning it as a string.
+pub(crate) async fn get_mapping_value_internal<N: Network>(
+    program_id: String,
+    mapping: String,
+    key: String,
+    endpoint: String,
+) -> anyhow::Result<Option<String>> {
+    let program_id = ProgramID::<N>::from_str(&program_id)
+        .map_err(|e| WalletError::InvalidInput(format!("program id: {e}")))?;
+    let mapping = Identifier::<N>::from_str(&mapping)
+        .map_err(|e| WalletError::InvalidInput(format!("mapping: {e}")))?;
+    let key = Plaintext::<N>::from_str(&key)
+        .map_err(|e| WalletError::InvalidInput(format!("key: {e}")))?;
+
+    let value = fetch_mapping_value(&endpoint, &program_id, &mapping, &key).await?;
+    Ok(value.map(|value| value.to_string()))
+}
+
+/// Lists the names of the mappings declared by the given program.
+pub(crate) async fn list_mappings_internal<N: Network>(
+    program_id: String,
+    endpoint: String,
+) -> anyhow::Result<Vec<String>> {
+    let program_id = ProgramID::<N>::from_str(&program_id)
+        .map_err(|e| WalletError::InvalidInput(format!("program id: {e}")))?;
+
+    let mappings = fetch_mapping_names(&endpoint, &program_id).await?;
+    Ok(mappings.iter().map(|mapping| mapping.to_string()).collect())
+}
+
+/// Fetches the value stored under `key` in the mapping, or `None` if the key is not in it.
+pub(crate) async fn fetch_mapping_value<N: Network>(
+    endpoint: &str,
+    program_id: &ProgramID<N>,
+    mapping: &Identifier<N>,
+    key: &Plaintext<N>,
+) -> anyhow::Result<Option<Value<N>>> {
+    // Keys such as structs contain spaces and braces, so they are encoded into the path.
+    let encoded_key = String::from(encode_uri_component(&key.to_string()));
+    let endpoint =
+        format!("{endpoint}/testnet3/program/{program_id}/mapping/{mapping}/{encoded_key}");
+    let resp_string = fetch_json(&endpoint, &format!("'{program_id}/{mapping}'")).await?;
+
+    // The node answers with the value as a JSON string, or `null` for a missing key.
+    let value: Option<String> = serde_json::from_str(&resp_string)
+        .map_err(|_| WalletError::Parse(format!("Unexpected response: {resp_string}")))?;
+    value
+        .map(|value| {
+            Value::<N>::from_str(&value).map_err(|e| {
+                WalletError::Parse(format!("value of '{program_id}/{mapping}': {e}")).into()
+            })
+        })
+        .transpose()
+}
+
+/// Fetches the names of the mappings declared by the program.
+pub(crate) async fn fetch_mapping_names<N: Network>(
+    endpoint: &str,
+    program_id: &ProgramID<N>,
+) -> anyhow::Result<Vec<Identifier<N>>> {
+    let endpoint = format!("{endpoint}/testnet3/program/{program_id}/mappings");
+    let resp_string = fetch_json(&endpoint, &format!("the mappings of '{program_id}'")).await?;
+
+    let names: Vec<String> = serde_json::from_str(&resp_string)
+        .map_err(|_| WalletError::Parse(format!("Unexpected response: {resp_string}")))?;
+    names
+        .iter()
+        .map(|name| {
+            Identifier::<N>::from_str(name)
+                .map_err(|e| WalletError::Parse(format!("mapping '{name}': {e}")).into())
+        })
+        .collect()
+}
+
+/// Fetches the response body of the endpoint, failing on any unsuccessful status.
+pub(crate) async fn fetch_json(endpoint: &str, what: &str) -> anyhow::Result<String> {
+    let resp: Response = get_request(endpoint, None).await?;
+    ensure!(
+        resp.ok(),
+        WalletError::Network {
+            status: Some(resp.status()),
+            message: format!("Failed to fetch {what}"),
+        }
+    );
+    response_text(resp).await
+}
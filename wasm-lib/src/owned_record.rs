@@ -0,0 +1,186 @@
+use crate::records::{OwnedCiphertext, RecordStatus};
+use crate::utils::record_microcredits;
+use js_sys::{Object, Reflect};
+use snarkvm_console_program::{Entry, Field, Network, Plaintext, Record};
+use wasm_bindgen::prelude::*;
+
+/// A decrypted record owned by the scanning account, along with where it was found.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct OwnedRecord {
+    owner: String,
+    microcredits: u64,
+    nonce: String,
+    commitment: String,
+    serial_number: Option<String>,
+    block_height: u32,
+    block_hash: String,
+    transaction_id: String,
+    transition_id: String,
+    program_id: String,
+    function_name: String,
+    output_index: u32,
+    plaintext: String,
+    data: Object,
+    status: RecordStatus,
+}
+
+impl OwnedRecord {
+    /// Describes the given decrypted record. The serial number is only known to the
+    /// holder of the private key.
+    pub(crate) fn new<N: Network>(
+        owned: &OwnedCiphertext<N>,
+        record: &Record<N, Plaintext<N>>,
+        serial_number: Option<Field<N>>,
+        status: RecordStatus,
+    ) -> Self {
+        OwnedRecord {
+            owner: (**record.owner()).to_string(),
+            microcredits: record_microcredits(record),
+            nonce: record.nonce().to_string(),
+            commitment: owned.commitment.to_string(),
+            serial_number: serial_number.map(|serial_number| serial_number.to_string()),
+            block_height: owned.height,
+            block_hash: owned.block_hash.to_string(),
+            transaction_id: owned.transaction_id.to_string(),
+            transition_id: owned.transition_id.to_string(),
+            program_id: owned.program_id.to_string(),
+            function_name: owned.function_name.to_string(),
+            output_index: owned.output_index,
+            plaintext: record.to_string(),
+            data: record_data(record),
+            status,
+        }
+    }
+
+    pub(crate) fn record_status(&self) -> RecordStatus {
+        self.status
+    }
+}
+
+#[wasm_bindgen]
+impl OwnedRecord {
+    /// The address of the owner.
+    #[wasm_bindgen(getter)]
+    pub fn owner(&self) -> String {
+        self.owner.clone()
+    }
+
+    /// The number of microcredits held by the record, as a `bigint`.
+    #[wasm_bindgen(getter)]
+    pub fn microcredits(&self) -> u64 {
+        self.microcredits
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nonce(&self) -> String {
+        self.nonce.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> String {
+        self.commitment.clone()
+    }
+
+    /// The serial number, if the record was scanned with a private key.
+    #[wasm_bindgen(getter)]
+    pub fn serial_number(&self) -> Option<String> {
+        self.serial_number.clone()
+    }
+
+    /// The height of the block the record was created in.
+    #[wasm_bindgen(getter)]
+    pub fn block_height(&self) -> u32 {
+        self.block_height
+    }
+
+    /// The hash of the block the record was created in.
+    #[wasm_bindgen(getter)]
+    pub fn block_hash(&self) -> String {
+        self.block_hash.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn transaction_id(&self) -> String {
+        self.transaction_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn transition_id(&self) -> String {
+        self.transition_id.clone()
+    }
+
+    /// The program whose execution created the record.
+    #[wasm_bindgen(getter)]
+    pub fn program_id(&self) -> String {
+        self.program_id.clone()
+    }
+
+    /// The function whose execution created the record.
+    #[wasm_bindgen(getter)]
+    pub fn function_name(&self) -> String {
+        self.function_name.clone()
+    }
+
+    /// The index of the record among the outputs of its transition.
+    #[wasm_bindgen(getter)]
+    pub fn output_index(&self) -> u32 {
+        self.output_index
+    }
+
+    /// The record in its plaintext form, which the execution functions accept as input.
+    #[wasm_bindgen(getter)]
+    pub fn plaintext(&self) -> String {
+        self.plaintext.clone()
+    }
+
+    /// The data entries of the record, as `{name: {visibility, value}}`. Struct values are
+    /// nested objects and literals are strings.
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Object {
+        self.data.clone()
+    }
+
+    /// Whether the record is `"unspent"`, `"spent"` or `"unknown"`. Records passed to a progress
+    /// callback are `"unknown"`, as their status is only checked once the scan completes.
+    #[wasm_bindgen(getter)]
+    pub fn status(&self) -> String {
+        self.status.as_str().to_string()
+    }
+}
+
+/// Converts the data entries of the record into a JS object.
+fn record_data<N: Network>(record: &Record<N, Plaintext<N>>) -> Object {
+    let data = Object::new();
+    for (name, entry) in record.data() {
+        let (visibility, value) = match entry {
+            Entry::Constant(value) => ("constant", value),
+            Entry::Public(value) => ("public", value),
+            Entry::Private(value) => ("private", value),
+        };
+
+        let js_entry = Object::new();
+        set(&js_entry, "visibility", JsValue::from_str(visibility));
+        set(&js_entry, "value", plaintext_value(value));
+        set(&data, &name.to_string(), js_entry.into());
+    }
+    data
+}
+
+/// Converts a plaintext value into a string, or an object for structs.
+fn plaintext_value<N: Network>(plaintext: &Plaintext<N>) -> JsValue {
+    match plaintext {
+        Plaintext::Literal(literal, _) => JsValue::from_str(&literal.to_string()),
+        Plaintext::Struct(members, _) => {
+            let object = Object::new();
+            for (name, member) in members {
+                set(&object, &name.to_string(), plaintext_value(member));
+            }
+            object.into()
+        }
+    }
+}
+
+fn set(object: &Object, key: &str, value: JsValue) {
+    let _ = Reflect::set(object, &JsValue::from_str(key), &value);
+}
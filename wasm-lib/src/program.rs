@@ -0,0 +1,145 @@
+use crate::error::WalletError;
+use crate::mapping::fetch_json;
+use crate::utils::{get_request, response_text};
+use anyhow::{bail, ensure};
+use indexmap::IndexMap;
+use snarkvm_console_program::{Identifier, Network, ProgramID};
+use snarkvm_synthesizer::{ConsensusMemory, Program, Transaction, VM};
+use web_sys::Response;
+
+/// Adds the given program and all of its imports to the VM's process, fetching the ones
+/// the process does not know about yet from the endpoint.
+pub(crate) async fn load_program<N: Network>(
+    vm: &VM<N, ConsensusMemory<N>>,
+    endpoint: &str,
+    program_id: &ProgramID<N>,
+) -> anyhow::Result<()> {
+    // Fetch the program and its missing imports.
+    let mut pending = vec![*program_id];
+    let mut programs = IndexMap::new();
+    while let Some(program_id) = pending.pop() {
+        if programs.contains_key(&program_id) || vm.process().read().contains_program(&program_id) {
+            continue;
+        }
+        let program = fetch_program::<N>(endpoint, &program_id).await?;
+        pending.extend(program.imports().keys().copied());
+        programs.insert(program_id, program);
+    }
+
+    add_programs(vm, programs)
+}
+
+/// Adds the programs to the VM's process, every import before the programs that use it.
+pub(crate) fn add_programs<N: Network>(
+    vm: &VM<N, ConsensusMemory<N>>,
+    mut programs: IndexMap<ProgramID<N>, Program<N>>,
+) -> anyhow::Result<()> {
+    let process = vm.process();
+    let mut process = process.write();
+    while !programs.is_empty() {
+        let index = programs
+            .values()
+            .position(|program| {
+                program
+                    .imports()
+                    .keys()
+                    .all(|import| process.contains_program(import))
+            })
+            .ok_or_else(|| {
+                WalletError::Parse("The program imports cannot be resolved".to_string())
+            })?;
+        if let Some((_, program)) = programs.shift_remove_index(index) {
+            process.add_program(&program)?;
+        }
+    }
+    Ok(())
+}
+
+/// Inserts the verifying keys of the given functions which the VM's process does not hold yet.
+/// The node serves only the source of a program, so the keys are taken from its deployment.
+pub(crate) async fn load_verifying_keys<N: Network>(
+    vm: &VM<N, ConsensusMemory<N>>,
+    endpoint: &str,
+    functions: &[(ProgramID<N>, Identifier<N>)],
+) -> anyhow::Result<()> {
+    let mut program_ids = Vec::new();
+    for (program_id, function_name) in functions {
+        let loaded = vm
+            .process()
+            .read()
+            .get_verifying_key(program_id, function_name)
+            .is_ok();
+        if !loaded && !program_ids.contains(program_id) {
+            program_ids.push(*program_id);
+        }
+    }
+
+    for program_id in &program_ids {
+        let transaction = fetch_deployment::<N>(endpoint, program_id).await?;
+        let deployment = match &transaction {
+            Transaction::Deploy(_, deployment, _) if deployment.program_id() == program_id => {
+                deployment
+            }
+            _ => bail!(WalletError::Parse(format!(
+                "The endpoint did not return the deployment of '{program_id}'"
+            ))),
+        };
+        let process = vm.process();
+        let process = process.read();
+        for (function_name, (verifying_key, _)) in deployment.verifying_keys() {
+            process.insert_verifying_key(program_id, function_name, verifying_key.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the transaction which deployed the given program from the endpoint.
+async fn fetch_deployment<N: Network>(
+    endpoint: &str,
+    program_id: &ProgramID<N>,
+) -> anyhow::Result<Transaction<N>> {
+    let what = format!("the deployment of '{program_id}'");
+    let resp_string = fetch_json(
+        &format!("{endpoint}/testnet3/find/transactionID/deployment/{program_id}"),
+        &what,
+    )
+    .await?;
+    let transaction_id: N::TransactionID = serde_json::from_str(&resp_string)
+        .map_err(|_| WalletError::Parse(format!("Unexpected response: {resp_string}")))?;
+
+    let resp_string = fetch_json(
+        &format!("{endpoint}/testnet3/transaction/{transaction_id}"),
+        &what,
+    )
+    .await?;
+    Ok(serde_json::from_str(&resp_string)
+        .map_err(|_| WalletError::Parse(format!("Failed to parse {what}")))?)
+}
+
+/// Fetches the source of the given program from the endpoint.
+async fn fetch_program<N: Network>(
+    endpoint: &str,
+    program_id: &ProgramID<N>,
+) -> anyhow::Result<Program<N>> {
+    let endpoint = format!("{endpoint}/testnet3/program/{program_id}");
+    let resp: Response = get_request(&endpoint, None).await?;
+    ensure!(
+        resp.ok(),
+        WalletError::Network {
+            status: Some(resp.status()),
+            message: format!("Failed to fetch program '{program_id}'"),
+        }
+    );
+
+    let resp_string = response_text(resp).await?;
+    let program: Program<N> = serde_json::from_str(&resp_string)
+        .map_err(|_| WalletError::Parse(format!("Failed to parse program '{program_id}'")))?;
+    ensure!(
+        program.id() == program_id,
+        WalletError::Parse(format!(
+            "The endpoint returned '{}' instead of '{program_id}'",
+            program.id()
+        ))
+    );
+    Ok(program)
+}
@@ -0,0 +1,282 @@
+use crate::error::WalletError;
+use anyhow::{bail, ensure};
+use std::ops::RangeInclusive;
+
+/// Resolves the heights to scan from the `start`, `end` and `last` arguments of a scan.
+///
+/// `start` is the first height to scan and `end` is the height to stop before, so that
+/// `start = 10, end = 20` scans the ten blocks from 10 to 19. Without an `end`, the scan runs up
+/// to and including `latest_height`. `last` scans the latest `last` blocks, or the whole chain
+/// if it is not that long yet.
+///
+/// `latest_height` is only needed when `end` is not given. If it is known, `end` is capped to it,
+/// as there is nothing to scan beyond the tip of the chain.
+pub(crate) fn block_range(
+    start: Option<u32>,
+    end: Option<u32>,
+    last: Option<u32>,
+    latest_height: Option<u32>,
+) -> anyhow::Result<RangeInclusive<u32>> {
+    let require_latest_height = || {
+        latest_height.ok_or_else(|| {
+            WalletError::Internal("The latest height is required for this range".to_string())
+        })
+    };
+
+    match (start, end, last) {
+        (start, Some(end), None) => {
+            let start = start.unwrap_or(0);
+            ensure!(
+                end > start,
+                WalletError::InvalidInput(format!(
+                    "The given scan range is invalid (start = {start}, end = {end})"
+                ))
+            );
+
+            match latest_height {
+                Some(latest_height) => {
+                    ensure!(
+                        start <= latest_height,
+                        WalletError::InvalidInput(format!(
+                            "The start height {start} is above the latest height {latest_height}"
+                        ))
+                    );
+                    Ok(start..=(end - 1).min(latest_height))
+                }
+                None => Ok(start..=end - 1),
+            }
+        }
+        (Some(start), None, None) => {
+            let latest_height = require_latest_height()?;
+            ensure!(
+                start <= latest_height,
+                WalletError::InvalidInput(format!(
+                    "The start height {start} is above the latest height {latest_height}"
+                ))
+            );
+
+            Ok(start..=latest_height)
+        }
+        (None, None, Some(last)) => {
+            ensure!(
+                last > 0,
+                WalletError::InvalidInput("`last` must be at least 1".to_string())
+            );
+            let latest_height = require_latest_height()?;
+
+            Ok(latest_height.saturating_sub(last - 1)..=latest_height)
+        }
+        (None, None, None) => bail!(WalletError::InvalidInput(
+            "Missing data about block range.".to_string()
+        )),
+        _ => bail!(WalletError::InvalidInput(
+            "`last` flags can't be used with `start` or `end`".to_string()
+        )),
+    }
+}
+
+/// Splits the given heights into consecutive windows of at most `window_size` blocks each.
+/// The windows are produced lazily, so that a long range is never held in memory at once.
+pub(crate) fn block_windows(
+    range: RangeInclusive<u32>,
+    window_size: u32,
+) -> impl Iterator<Item = RangeInclusive<u32>> {
+    let is_empty = range.is_empty() || window_size == 0;
+    let (start_height, end_height) = range.into_inner();
+    let window = move |window_start: u32| {
+        window_start..=window_start.saturating_add(window_size - 1).min(end_height)
+    };
+
+    let first = if is_empty {
+        None
+    } else {
+        Some(window(start_height))
+    };
+    std::iter::successors(first, move |previous| {
+        let previous_end = *previous.end();
+        (previous_end < end_height).then(|| window(previous_end + 1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_range, block_windows};
+    use crate::error::WalletError;
+    use std::ops::RangeInclusive;
+
+    fn range_error(
+        start: Option<u32>,
+        end: Option<u32>,
+        last: Option<u32>,
+        latest_height: Option<u32>,
+    ) -> WalletError {
+        WalletError::from(block_range(start, end, last, latest_height).unwrap_err())
+    }
+
+    #[test]
+    fn test_block_range_start_and_end() {
+        // The end is exclusive.
+        assert_eq!(block_range(Some(0), Some(10), None, None).unwrap(), 0..=9);
+        assert_eq!(block_range(Some(5), Some(6), None, None).unwrap(), 5..=5);
+        assert_eq!(block_range(None, Some(10), None, None).unwrap(), 0..=9);
+        assert_eq!(block_range(None, Some(1), None, None).unwrap(), 0..=0);
+        assert_eq!(
+            block_range(Some(0), Some(u32::MAX), None, None).unwrap(),
+            0..=u32::MAX - 1
+        );
+
+        // The end is capped to the latest height, if it is known.
+        assert_eq!(
+            block_range(Some(5), Some(10), None, Some(100)).unwrap(),
+            5..=9
+        );
+        assert_eq!(
+            block_range(Some(0), Some(u32::MAX), None, Some(100)).unwrap(),
+            0..=100
+        );
+        assert_eq!(
+            block_range(Some(100), Some(200), None, Some(100)).unwrap(),
+            100..=100
+        );
+        assert_eq!(
+            range_error(Some(101), Some(200), None, Some(100)).code(),
+            "INVALID_INPUT"
+        );
+
+        // Empty and reversed ranges are rejected.
+        assert_eq!(
+            range_error(Some(5), Some(5), None, None).code(),
+            "INVALID_INPUT"
+        );
+        assert_eq!(
+            range_error(Some(6), Some(5), None, None).code(),
+            "INVALID_INPUT"
+        );
+        assert_eq!(
+            range_error(None, Some(0), None, None).code(),
+            "INVALID_INPUT"
+        );
+    }
+
+    #[test]
+    fn test_block_range_up_to_latest() {
+        // Without an end, the latest height is included.
+        assert_eq!(
+            block_range(Some(0), None, None, Some(100)).unwrap(),
+            0..=100
+        );
+        assert_eq!(
+            block_range(Some(100), None, None, Some(100)).unwrap(),
+            100..=100
+        );
+        assert_eq!(block_range(Some(0), None, None, Some(0)).unwrap(), 0..=0);
+        assert_eq!(
+            range_error(Some(101), None, None, Some(100)).code(),
+            "INVALID_INPUT"
+        );
+    }
+
+    #[test]
+    fn test_block_range_last() {
+        assert_eq!(
+            block_range(None, None, Some(1), Some(100)).unwrap(),
+            100..=100
+        );
+        assert_eq!(
+            block_range(None, None, Some(10), Some(100)).unwrap(),
+            91..=100
+        );
+        assert_eq!(
+            block_range(None, None, Some(101), Some(100)).unwrap(),
+            0..=100
+        );
+
+        // `last` beyond the length of the chain scans the whole chain.
+        assert_eq!(
+            block_range(None, None, Some(1000), Some(100)).unwrap(),
+            0..=100
+        );
+        assert_eq!(
+            block_range(None, None, Some(u32::MAX), Some(0)).unwrap(),
+            0..=0
+        );
+
+        assert_eq!(
+            range_error(None, None, Some(0), Some(100)).code(),
+            "INVALID_INPUT"
+        );
+    }
+
+    #[test]
+    fn test_block_range_invalid_arguments() {
+        assert_eq!(
+            range_error(None, None, None, Some(100)).code(),
+            "INVALID_INPUT"
+        );
+        assert_eq!(
+            range_error(Some(0), None, Some(10), Some(100)).code(),
+            "INVALID_INPUT"
+        );
+        assert_eq!(
+            range_error(None, Some(10), Some(10), Some(100)).code(),
+            "INVALID_INPUT"
+        );
+        assert_eq!(
+            range_error(Some(0), Some(10), Some(10), Some(100)).code(),
+            "INVALID_INPUT"
+        );
+
+        // Ranges relative to the tip need the latest height.
+        assert_eq!(range_error(Some(0), None, None, None).code(), "INTERNAL");
+    }
+
+    fn windows(range: RangeInclusive<u32>, window_size: u32) -> Vec<RangeInclusive<u32>> {
+        block_windows(range, window_size).collect()
+    }
+
+    #[test]
+    fn test_block_windows() {
+        assert_eq!(windows(7..=7, 50), vec![7..=7]);
+        assert_eq!(windows(0..=49, 50), vec![0..=49]);
+        assert_eq!(windows(0..=50, 50), vec![0..=49, 50..=50]);
+        assert_eq!(windows(0..=99, 50), vec![0..=49, 50..=99]);
+        assert_eq!(windows(3..=5, 1), vec![3..=3, 4..=4, 5..=5]);
+        assert_eq!(
+            windows(u32::MAX - 60..=u32::MAX, 50),
+            vec![u32::MAX - 60..=u32::MAX - 11, u32::MAX - 10..=u32::MAX]
+        );
+        assert_eq!(windows(0..=u32::MAX, u32::MAX).len(), 2);
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 5..=4;
+        assert!(windows(empty, 50).is_empty());
+        assert!(windows(0..=10, 0).is_empty());
+
+        // The windows of the whole chain are produced one at a time.
+        let mut all = block_windows(0..=u32::MAX, 50);
+        assert_eq!(all.next(), Some(0..=49));
+        assert_eq!(all.next(), Some(50..=99));
+    }
+
+    #[test]
+    fn test_block_windows_cover_range() {
+        for start in 0..20 {
+            for end in start..40 {
+                for window_size in 1..12 {
+                    let windows = windows(start..=end, window_size);
+
+                    // The windows cover the range exactly once, in order.
+                    let heights = windows.iter().cloned().flatten().collect::<Vec<_>>();
+                    assert_eq!(heights, (start..=end).collect::<Vec<_>>());
+
+                    // Only the last window may be short.
+                    let (last, full) = windows.split_last().unwrap();
+                    assert!(full
+                        .iter()
+                        .all(|window| window.clone().count() == window_size as usize));
+                    assert!(last.clone().count() <= window_size as usize);
+                }
+            }
+        }
+    }
+}
@@ -1,49 +1,98 @@
-use crate::utils::{get_request, parse_account};
+use crate::error::WalletError;
+use crate::owned_record::OwnedRecord;
+use crate::range::{block_range, block_windows};
+use crate::utils::{ensure_not_aborted, get_request, parse_account, response_text};
 use anyhow::{bail, ensure};
-use js_sys::Array;
+use futures::stream::{self, StreamExt};
+use js_sys::{Array, Function, Object, Reflect};
+use serde::{Deserialize, Serialize};
 use snarkvm_console_account::{PrivateKey, ViewKey};
-use snarkvm_console_program::{Ciphertext, Field, Network, Plaintext, Record};
-use snarkvm_synthesizer::Block;
+use snarkvm_console_program::{
+    Ciphertext, Field, Identifier, Network, Plaintext, ProgramID, Record,
+};
+use snarkvm_synthesizer::{Block, Output};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 
 use wasm_bindgen::prelude::*;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::Response;
+use web_sys::{AbortSignal, Response};
+
+/// The maximum number of spent checks in flight at once.
+const MAX_CONCURRENT_REQUESTS: usize = 16;
+
+/// The default number of blocks requested at once.
+const DEFAULT_WINDOW_SIZE: u32 = 50;
+
+/// The default number of block windows in flight at once.
+const DEFAULT_CONCURRENT_WINDOWS: usize = 4;
 
 #[wasm_bindgen]
 #[derive(Debug)]
 pub struct RecordScanner {
-    msg: String,
     records: Array,
 }
 
 #[wasm_bindgen]
 impl RecordScanner {
     #[wasm_bindgen(constructor)]
-    pub fn new(msg: String, records: Array) -> Self {
-        RecordScanner { msg, records }
-    }
-
-    #[wasm_bindgen(getter)]
-    pub fn msg(&self) -> String {
-        self.msg.clone()
+    pub fn new(records: Array) -> Self {
+        RecordScanner { records }
     }
 
+    /// The `OwnedRecord`s found by the scan.
     #[wasm_bindgen(getter)]
     pub fn records(&self) -> Array {
         self.records.clone()
     }
 
-    #[wasm_bindgen(setter)]
-    pub fn set_msg(&mut self, msg: String) {
-        self.msg = msg
-    }
-
     #[wasm_bindgen(setter)]
     pub fn set_records(&mut self, records: Array) {
         self.records = records
     }
 }
 
+/// How the scanner finds out whether an owned record has been spent.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpentCheck {
+    /// Look up the serial number of every owned record on the node.
+    #[default]
+    Remote,
+    /// Match the serial numbers against the inputs of the scanned blocks, so that no
+    /// serial number ever leaves the browser. Spends after the scanned range are not seen.
+    Local,
+}
+
+/// Whether an owned record has been spent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum RecordStatus {
+    Unspent,
+    Spent,
+    /// The spent check failed, or no private key was given to compute the serial number.
+    Unknown,
+}
+
+impl RecordStatus {
+    /// Maps the HTTP status of a `find/transitionID/{serial_number}` lookup onto the record status.
+    fn from_http_status(status: u16) -> Self {
+        match status {
+            200..=299 => RecordStatus::Spent,
+            404 => RecordStatus::Unspent,
+            _ => RecordStatus::Unknown,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            RecordStatus::Unspent => "unspent",
+            RecordStatus::Spent => "spent",
+            RecordStatus::Unknown => "unknown",
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn request_records_internal<N: Network>(
     private_key: Option<String>,
     view_key: String,
@@ -51,242 +100,552 @@ pub(crate) async fn request_records_internal<N: Network>(
     end: Option<u32>,
     last: Option<u32>,
     endpoint: String,
-) -> anyhow::Result<Vec<String>> {
+    spent_check: SpentCheck,
+    fetch: BlockFetch,
+    callback: Option<Function>,
+    signal: Option<AbortSignal>,
+) -> anyhow::Result<Vec<OwnedRecord>> {
     // Derive the view key and optional private key.
     let (private_key, view_key) = parse_account::<N>(private_key, view_key)?;
 
     // Find the start and end height to scan.
-    let (start_height, end_height) = parse_block_range(start, end, last, endpoint.clone()).await?;
+    let (start_height, end_height, latest_height) =
+        parse_block_range(start, end, last, endpoint.clone(), signal.as_ref()).await?;
 
     // Fetch the records_web from the network.
-    let records =
-        fetch_records::<N>(private_key, &view_key, &endpoint, start_height, end_height).await?;
+    fetch_records::<N>(
+        private_key,
+        &view_key,
+        &endpoint,
+        start_height,
+        end_height,
+        end_height >= latest_height,
+        spent_check,
+        fetch,
+        ScanProgress::new(callback.as_ref(), start_height, end_height),
+        signal.as_ref(),
+    )
+    .await
+}
 
-    // Output the decrypted records_web associated with the view key.
-    if records.is_empty() {
-        Err(anyhow::Error::msg("No records_web found".to_string()))
-    } else {
-        let mut res = Vec::new();
-        for r in records {
-            let s = serde_json::to_string_pretty(&r)?.replace("\\n", "");
-            println!("{}", s);
-            res.push(s)
-        }
-        Ok(res)
-    }
+/// Reports the progress of a scan to an optional JS callback.
+///
+/// After every batch of blocks the callback is called with `{scanned, total, found}`, and every
+/// owned record is passed on as soon as it is found as `{scanned, total, found, record}`, with
+/// `record` an `OwnedRecord`. The spent status and serial number of a record are only known once
+/// the scan completes.
+pub(crate) struct ScanProgress<'a> {
+    callback: Option<&'a Function>,
+    total: u32,
 }
 
-async fn parse_block_range(
-    start: Option<u32>,
-    end: Option<u32>,
-    last: Option<u32>,
-    endpoint: String,
-) -> anyhow::Result<(u32, u32)> {
-    match (start, end, last) {
-        (Some(start), Some(end), None) => {
-            ensure!(
-                end > start,
-                "The given scan range is invalid (start = {start}, end = {end})"
-            );
+impl<'a> ScanProgress<'a> {
+    pub(crate) fn new(callback: Option<&'a Function>, start_height: u32, end_height: u32) -> Self {
+        ScanProgress {
+            callback,
+            total: end_height.saturating_sub(start_height).saturating_add(1),
+        }
+    }
 
-            Ok((start, end))
+    /// A scan which reports to nobody.
+    pub(crate) fn none() -> Self {
+        ScanProgress {
+            callback: None,
+            total: 0,
         }
-        (Some(start), None, None) => {
-            // Request the latest block height from the endpoint.
-            let endpoint = format!("{}/testnet3/latest/height", endpoint);
-            let latest_height = fetch_latest_height(&endpoint).await?;
+    }
 
-            // Print warning message if the user is attempting to scan the whole chain.
-            // if start == 0 {
-            //     println!("⚠️  Attention - Scanning the entire chain. This may take a while...\n");
-            // }
+    fn is_enabled(&self) -> bool {
+        self.callback.is_some()
+    }
 
-            Ok((start, latest_height))
-        }
-        (None, Some(end), None) => Ok((0, end)),
-        (None, None, Some(last)) => {
-            // Request the latest block height from the endpoint.
-            let endpoint = format!("{}/testnet3/latest/height", endpoint);
-            let latest_height = fetch_latest_height(&endpoint).await?;
+    fn report(&self, scanned: u32, found: usize) -> anyhow::Result<()> {
+        self.call(scanned, found, None)
+    }
+
+    fn record(&self, scanned: u32, found: usize, record: OwnedRecord) -> anyhow::Result<()> {
+        self.call(scanned, found, Some(record))
+    }
 
-            Ok((latest_height.saturating_sub(last), latest_height))
+    fn call(&self, scanned: u32, found: usize, record: Option<OwnedRecord>) -> anyhow::Result<()> {
+        let callback = match self.callback {
+            Some(callback) => callback,
+            None => return Ok(()),
+        };
+
+        let event = Object::new();
+        let set = |key: &str, value: JsValue| {
+            let _ = Reflect::set(&event, &JsValue::from_str(key), &value);
+        };
+        set("scanned", scanned.into());
+        set("total", self.total.into());
+        set("found", (found as u32).into());
+        if let Some(record) = record {
+            set("record", record.into());
         }
-        (None, None, None) => bail!("Missing data about block range."),
-        _ => bail!("`last` flags can't be used with `start` or `end`"),
+
+        callback.call1(&JsValue::NULL, &event).map_err(|js_value| {
+            WalletError::Internal(format!("progress callback failed: {:?}", js_value))
+        })?;
+        Ok(())
     }
 }
 
-async fn fetch_latest_height(endpoint: &str) -> anyhow::Result<u32> {
-    let resp: Response = get_request(endpoint).await?;
+/// Resolves the range to scan, returning its start and end height along with the latest height.
+async fn parse_block_range(
+    start: Option<u32>,
+    end: Option<u32>,
+    last: Option<u32>,
+    endpoint: String,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<(u32, u32, u32)> {
+    // Request the latest block height from the endpoint. Besides resolving ranges relative to
+    // it, it tells whether the scan reaches the tip of the chain.
+    let endpoint = format!("{}/testnet3/latest/height", endpoint);
+    let latest_height = fetch_latest_height(&endpoint, signal).await?;
+
+    let (start_height, end_height) =
+        block_range(start, end, last, Some(latest_height))?.into_inner();
+    Ok((start_height, end_height, latest_height))
+}
+
+pub(crate) async fn fetch_latest_height(
+    endpoint: &str,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<u32> {
+    let resp: Response = get_request(endpoint, signal).await?;
 
     if resp.ok() {
-        let resp = match resp.text() {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(anyhow::Error::msg(err.as_string().unwrap_or_default()));
-            }
-        };
-        let resp_text = match JsFuture::from(resp).await {
-            Ok(text) => text,
-            Err(err) => {
-                return Err(anyhow::Error::msg(err.as_string().unwrap_or_default()));
-            }
-        };
-        let resp_string: String = match resp_text.as_string() {
-            None => {
-                return Err(anyhow::Error::msg("failed to convert resp_test to string"));
-            }
-            Some(s) => s,
-        };
+        let resp_string = response_text(resp).await?;
         let latest_height: u32 = resp_string
             .parse()
-            .map_err(|_| anyhow::Error::msg("Failed to parse u32 from response"))?;
+            .map_err(|_| WalletError::Parse("Failed to parse u32 from response".to_string()))?;
         Ok(latest_height)
     } else {
-        Err(anyhow::Error::msg("Fetch request failed."))
+        Err(fetch_failed(&resp).into())
     }
 }
 
 /// Fetch owned ciphertext records_web from the endpoint.
+#[allow(clippy::too_many_arguments)]
 async fn fetch_records<N: Network>(
     private_key: Option<PrivateKey<N>>,
     view_key: &ViewKey<N>,
     endpoint: &str,
     start_height: u32,
     end_height: u32,
-) -> anyhow::Result<Vec<Record<N, Plaintext<N>>>> {
-    // Check the bounds of the request.
-    if start_height > end_height {
-        bail!("Invalid block range");
+    scanned_to_tip: bool,
+    spent_check: SpentCheck,
+    fetch: BlockFetch,
+    progress: ScanProgress<'_>,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<Vec<OwnedRecord>> {
+    let scan = scan_blocks(
+        view_key,
+        endpoint,
+        start_height,
+        end_height,
+        None,
+        spent_check,
+        fetch,
+        progress,
+        signal,
+    )
+    .await?;
+
+    // Check which of the owned records_web are spent.
+    let commitments = scan
+        .owned_records
+        .iter()
+        .map(|owned| owned.commitment)
+        .collect::<Vec<_>>();
+    let lookup = match spent_check {
+        SpentCheck::Remote => SerialNumberLookup::Remote(endpoint),
+        SpentCheck::Local => SerialNumberLookup::Local {
+            spent_serial_numbers: &scan.spent_serial_numbers,
+            scanned_to_tip,
+        },
+    };
+    let statuses = record_statuses(private_key, lookup, &commitments, signal).await?;
+
+    // Decrypt the records_web which are not spent.
+    let mut records = Vec::new();
+    for (owned, status) in scan.owned_records.iter().zip(statuses) {
+        // Skip the record as it is spent.
+        if status != RecordStatus::Spent {
+            let serial_number = private_key
+                .map(|private_key| {
+                    Record::<N, Plaintext<N>>::serial_number(private_key, owned.commitment)
+                })
+                .transpose()?;
+            let record = owned.record.decrypt(view_key)?;
+            records.push(OwnedRecord::new(owned, &record, serial_number, status));
+        }
     }
+    Ok(records)
+}
 
-    // Derive the x-coordinate of the address corresponding to the given view key.
-    let address_x_coordinate = view_key.to_address().to_x_coordinate();
+/// The maximum number of recent block hashes kept to detect and roll back reorgs.
+pub(crate) const MAX_REORG_DEPTH: usize = 100;
+
+/// A ciphertext record owned by the view key.
+pub(crate) struct OwnedCiphertext<N: Network> {
+    /// The height of the block the record was created in.
+    pub(crate) height: u32,
+    pub(crate) block_hash: N::BlockHash,
+    pub(crate) transaction_id: N::TransactionID,
+    pub(crate) transition_id: N::TransitionID,
+    /// The program and function of the transition which output the record.
+    pub(crate) program_id: ProgramID<N>,
+    pub(crate) function_name: Identifier<N>,
+    /// The index of the record among the outputs of the transition.
+    pub(crate) output_index: u32,
+    pub(crate) commitment: Field<N>,
+    pub(crate) record: Record<N, Ciphertext<N>>,
+}
 
-    const MAX_BLOCK_RANGE: u32 = 50;
+/// The result of scanning a range of blocks.
+pub(crate) struct BlockScan<N: Network> {
+    /// The records owned by the view key.
+    pub(crate) owned_records: Vec<OwnedCiphertext<N>>,
+    /// The serial numbers spent in the scanned blocks, and the heights they were spent at.
+    /// They are collected for the local spent check only.
+    pub(crate) spent_serial_numbers: HashMap<Field<N>, u32>,
+    /// The heights and hashes of the last scanned blocks, at most `MAX_REORG_DEPTH` of them.
+    pub(crate) block_hashes: VecDeque<(u32, N::BlockHash)>,
+}
 
-    let mut records = Vec::new();
+/// A scanned block does not link up to the hash of the block before it.
+#[derive(Debug)]
+pub(crate) struct ChainMismatch {
+    /// The height of the block which does not link up.
+    pub(crate) height: u32,
+}
 
-    // Calculate the number of blocks to scan.
-    // let total_blocks = end_height.saturating_sub(start_height);
+impl fmt::Display for ChainMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The block at height {} does not link to the previous block",
+            self.height
+        )
+    }
+}
 
-    // Scan the endpoint starting from the start height
-    let mut request_start = start_height;
-    while request_start <= end_height {
-        // // Log the progress.
-        // let percentage_complete = request_start.saturating_sub(start_height) as f64 * 100.0 / total_blocks as f64;
-        // print!("\rScanning {total_blocks} blocks for records_web ({percentage_complete:.2}% complete)...");
-        // stdout().flush()?;
+impl std::error::Error for ChainMismatch {}
 
-        let num_blocks_to_request = std::cmp::min(
-            MAX_BLOCK_RANGE,
-            end_height.saturating_sub(request_start).saturating_add(1),
+/// How the blocks of a scan are fetched from the endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BlockFetch {
+    /// The number of blocks requested at once.
+    window_size: u32,
+    /// The number of windows in flight at once.
+    concurrency: usize,
+}
+
+impl Default for BlockFetch {
+    fn default() -> Self {
+        BlockFetch {
+            window_size: DEFAULT_WINDOW_SIZE,
+            concurrency: DEFAULT_CONCURRENT_WINDOWS,
+        }
+    }
+}
+
+impl BlockFetch {
+    pub(crate) fn new(
+        window_size: Option<u32>,
+        concurrency: Option<usize>,
+    ) -> anyhow::Result<Self> {
+        let fetch = BlockFetch {
+            window_size: window_size.unwrap_or(DEFAULT_WINDOW_SIZE),
+            concurrency: concurrency.unwrap_or(DEFAULT_CONCURRENT_WINDOWS),
+        };
+        ensure!(
+            fetch.window_size > 0 && fetch.concurrency > 0,
+            WalletError::InvalidInput(format!(
+                "The window size ({}) and concurrency ({}) must be positive",
+                fetch.window_size, fetch.concurrency
+            ))
         );
-        let request_end = request_start.saturating_add(num_blocks_to_request);
+        Ok(fetch)
+    }
+}
+
+/// Scans the blocks in the given range for records owned by the view key. Every block must link
+/// to the one before it, starting with `previous_hash` as the hash of the block before the range.
+///
+/// Several windows of blocks are fetched at once, but they are always scanned in height order.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn scan_blocks<N: Network>(
+    view_key: &ViewKey<N>,
+    endpoint: &str,
+    start_height: u32,
+    end_height: u32,
+    previous_hash: Option<N::BlockHash>,
+    spent_check: SpentCheck,
+    fetch: BlockFetch,
+    progress: ScanProgress<'_>,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<BlockScan<N>> {
+    // Check the bounds of the request.
+    if start_height > end_height {
+        bail!(WalletError::InvalidInput("Invalid block range".to_string()));
+    }
 
-        // Establish the endpoint.
-        let blocks_endpoint =
-            format!("{endpoint}/testnet3/blocks?start={request_start}&end={request_end}");
+    // Derive the x-coordinate of the address corresponding to the given view key.
+    let address_x_coordinate = view_key.to_address().to_x_coordinate();
+
+    let mut scan = BlockScan {
+        owned_records: Vec::new(),
+        spent_serial_numbers: HashMap::new(),
+        block_hashes: VecDeque::new(),
+    };
+    let mut previous_hash = previous_hash;
+
+    // Fetch the windows ahead of time, but yield them in order.
+    let mut windows = stream::iter(block_windows(start_height..=end_height, fetch.window_size))
+        .map(|window| async move {
+            // Establish the endpoint. Its end height is exclusive.
+            let (request_start, request_end) = window.into_inner();
+            let blocks_endpoint = format!(
+                "{endpoint}/testnet3/blocks?start={request_start}&end={}",
+                u64::from(request_end) + 1
+            );
 
-        // Fetch blocks
-        let blocks: Vec<Block<N>> = fetch_blocks(&blocks_endpoint).await?;
+            // Fetch blocks
+            let blocks: Vec<Block<N>> = fetch_blocks(&blocks_endpoint, signal).await?;
+            anyhow::Ok((request_start, request_end, blocks))
+        })
+        .buffered(fetch.concurrency);
+
+    while let Some(window) = windows.next().await {
+        // Stop between batches if the caller aborted the scan.
+        ensure_not_aborted(signal)?;
+        let (request_start, request_end, blocks) = window?;
 
         // Scan the blocks for owned records_web.
         for block in &blocks {
-            for (commitment, ciphertext_record) in block.records() {
-                // Check if the record is owned by the given view key.
-                if ciphertext_record
-                    .is_owner_with_address_x_coordinate(view_key, &address_x_coordinate)
-                {
-                    // Decrypt and optionally filter the records_web.
-                    if let Some(record) = decrypt_record(
-                        private_key,
-                        view_key,
-                        endpoint,
-                        *commitment,
-                        ciphertext_record,
-                    )
-                    .await?
-                    {
-                        records.push(record);
+            // Check that the block extends the chain scanned so far.
+            if let Some(previous_hash) = previous_hash {
+                if block.previous_hash() != previous_hash {
+                    bail!(ChainMismatch {
+                        height: block.height()
+                    });
+                }
+            }
+            previous_hash = Some(block.hash());
+
+            for transaction in block.transactions().values() {
+                for transition in transaction.transitions() {
+                    for (output_index, output) in transition.outputs().iter().enumerate() {
+                        let (commitment, ciphertext_record) = match output {
+                            Output::Record(commitment, _, Some(record)) => (commitment, record),
+                            _ => continue,
+                        };
+
+                        // Check if the record is owned by the given view key.
+                        if !ciphertext_record
+                            .is_owner_with_address_x_coordinate(view_key, &address_x_coordinate)
+                        {
+                            continue;
+                        }
+
+                        let owned = OwnedCiphertext {
+                            height: block.height(),
+                            block_hash: block.hash(),
+                            transaction_id: transaction.id(),
+                            transition_id: *transition.id(),
+                            program_id: *transition.program_id(),
+                            function_name: *transition.function_name(),
+                            output_index: output_index as u32,
+                            commitment: *commitment,
+                            record: ciphertext_record.clone(),
+                        };
+
+                        // Pass the record on right away, if anyone is listening.
+                        if progress.is_enabled() {
+                            let record = owned.record.decrypt(view_key)?;
+                            progress.record(
+                                request_start.saturating_sub(start_height),
+                                scan.owned_records.len() + 1,
+                                OwnedRecord::new(&owned, &record, None, RecordStatus::Unknown),
+                            )?;
+                        }
+                        scan.owned_records.push(owned);
                     }
                 }
             }
+
+            // Collect the serial numbers spent in the block.
+            if spent_check == SpentCheck::Local {
+                scan.spent_serial_numbers.extend(
+                    block
+                        .serial_numbers()
+                        .map(|serial_number| (*serial_number, block.height())),
+                );
+            }
+            scan.block_hashes.push_back((block.height(), block.hash()));
+            if scan.block_hashes.len() > MAX_REORG_DEPTH {
+                scan.block_hashes.pop_front();
+            }
         }
 
-        request_start = request_start.saturating_add(num_blocks_to_request);
+        // Log the progress.
+        progress.report(
+            request_end.saturating_sub(start_height).saturating_add(1),
+            scan.owned_records.len(),
+        )?;
     }
 
-    // Print final complete message.
-    // println!("\rScanning {total_blocks} blocks for records_web (100% complete)...   \n");
-    // stdout().flush()?;
+    Ok(scan)
+}
 
-    Ok(records)
+/// Where the serial numbers of the owned records are looked up.
+pub(crate) enum SerialNumberLookup<'a, N: Network> {
+    /// On the node at the given endpoint.
+    Remote(&'a str),
+    /// In the serial numbers spent within the scanned blocks. A serial number missing from them
+    /// only means the record is unspent if the scan reached the tip of the chain.
+    Local {
+        spent_serial_numbers: &'a HashMap<Field<N>, u32>,
+        scanned_to_tip: bool,
+    },
 }
 
-/// Decrypts the ciphertext record and filters spend record if a private key was provided.
-async fn decrypt_record<N: Network>(
+/// Determines the spent status of the records with the given commitments,
+/// if a private key was provided to compute their serial numbers.
+pub(crate) async fn record_statuses<N: Network>(
     private_key: Option<PrivateKey<N>>,
-    view_key: &ViewKey<N>,
-    endpoint: &str,
-    commitment: Field<N>,
-    ciphertext_record: &Record<N, Ciphertext<N>>,
-) -> anyhow::Result<Option<Record<N, Plaintext<N>>>> {
+    lookup: SerialNumberLookup<'_, N>,
+    commitments: &[Field<N>],
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<Vec<RecordStatus>> {
     // Check if a private key was provided.
-    if let Some(private_key) = private_key {
-        // Compute the serial number.
-        let serial_number = Record::<N, Plaintext<N>>::serial_number(private_key, commitment)?;
-
-        // Establish the endpoint.
-        let endpoint = format!("{endpoint}/testnet3/find/transitionID/{serial_number}");
-
-        // Check if the record is spent.
-        match get_request(&endpoint).await {
-            // On success, skip as the record is spent.
-            Ok(_) => Ok(None),
-            // On error, add the record.
-            Err(_error) => {
-                // TODO: Dedup the error types. We're adding the record as valid because the endpoint failed,
-                //  meaning it couldn't find the serial number (ie. unspent). However if there's a DNS error or request error,
-                //  we have a false positive here then.
-                // Decrypt the record.
-                Ok(Some(ciphertext_record.decrypt(view_key)?))
-            }
+    let private_key = match private_key {
+        Some(private_key) => private_key,
+        // If no private key was provided, the spent status cannot be checked.
+        None => return Ok(vec![RecordStatus::Unknown; commitments.len()]),
+    };
+
+    // Compute the serial numbers.
+    let serial_numbers = commitments
+        .iter()
+        .map(|commitment| Record::<N, Plaintext<N>>::serial_number(private_key, *commitment))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let statuses = match lookup {
+        // Check if the records are spent, with a bounded number of requests in flight.
+        // The node has no bulk lookup, so each serial number is looked up on its own.
+        SerialNumberLookup::Remote(endpoint) => {
+            let statuses = stream::iter(serial_numbers)
+                .map(|serial_number| fetch_record_status(endpoint, serial_number, signal))
+                .buffered(MAX_CONCURRENT_REQUESTS)
+                .collect::<Vec<_>>()
+                .await;
+            // Aborted lookups must not pass for unknown statuses.
+            ensure_not_aborted(signal)?;
+            statuses
         }
+        // Check if the records are spent within the scanned blocks.
+        SerialNumberLookup::Local {
+            spent_serial_numbers,
+            scanned_to_tip,
+        } => serial_numbers
+            .iter()
+            .map(|serial_number| {
+                if spent_serial_numbers.contains_key(serial_number) {
+                    RecordStatus::Spent
+                } else if scanned_to_tip {
+                    RecordStatus::Unspent
+                } else {
+                    // The record may have been spent in a block after the scanned ones.
+                    RecordStatus::Unknown
+                }
+            })
+            .collect(),
+    };
+    Ok(statuses)
+}
+
+/// Looks up whether the record with the given serial number is spent.
+async fn fetch_record_status<N: Network>(
+    endpoint: &str,
+    serial_number: Field<N>,
+    signal: Option<&AbortSignal>,
+) -> RecordStatus {
+    // Establish the endpoint.
+    let endpoint = format!("{endpoint}/testnet3/find/transitionID/{serial_number}");
+
+    // Only a 404 means the serial number is unknown to the node (ie. unspent);
+    // failed requests and other statuses leave the status unknown.
+    match get_request(&endpoint, signal).await {
+        Ok(response) => RecordStatus::from_http_status(response.status()),
+        Err(_error) => RecordStatus::Unknown,
+    }
+}
+
+/// Fetches the block at the given height.
+pub(crate) async fn fetch_block<N: Network>(
+    endpoint: &str,
+    height: u32,
+) -> anyhow::Result<Block<N>> {
+    let resp: Response = get_request(&format!("{endpoint}/testnet3/block/{height}"), None).await?;
+
+    if resp.ok() {
+        let resp_string = response_text(resp).await?;
+
+        let block = serde_json::from_str(&resp_string)
+            .map_err(|_| WalletError::Parse("Failed to parse Block from response".to_string()))?;
+        Ok(block)
     } else {
-        // If no private key was provided, return the record.
-        Ok(Some(ciphertext_record.decrypt(view_key)?))
+        Err(fetch_failed(&resp).into())
     }
 }
 
-async fn fetch_blocks<N: Network>(endpoint: &str) -> anyhow::Result<Vec<Block<N>>> {
-    let resp: Response = get_request(endpoint).await?;
+async fn fetch_blocks<N: Network>(
+    endpoint: &str,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<Vec<Block<N>>> {
+    let resp: Response = get_request(endpoint, signal).await?;
 
     if resp.ok() {
-        let resp = match resp.text() {
-            Ok(res) => res,
-            Err(err) => {
-                return Err(anyhow::Error::msg(err.as_string().unwrap_or_default()));
-            }
-        };
-        let resp_text = match JsFuture::from(resp).await {
-            Ok(text) => text,
-            Err(err) => {
-                return Err(anyhow::Error::msg(err.as_string().unwrap_or_default()));
-            }
-        };
-        let resp_string: String = match resp_text.as_string() {
-            None => {
-                return Err(anyhow::Error::msg("failed to convert resp_test to string"));
-            }
-            Some(s) => s,
-        };
+        let resp_string = response_text(resp).await?;
 
         let blocks = serde_json::from_str(&resp_string)
-            .map_err(|_| anyhow::Error::msg("Failed to parse Block from response"))?;
+            .map_err(|_| WalletError::Parse("Failed to parse Block from response".to_string()))?;
         Ok(blocks)
     } else {
-        Err(anyhow::Error::msg("Fetch request failed."))
+        Err(fetch_failed(&resp).into())
+    }
+}
+
+fn fetch_failed(resp: &Response) -> WalletError {
+    WalletError::Network {
+        status: Some(resp.status()),
+        message: "Fetch request failed.".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::RecordStatus;
+
+    #[test]
+    fn test_record_status_from_http_status() {
+        // Any success means the node found a transition spending the serial number.
+        for status in [200, 201, 204, 299] {
+            assert_eq!(RecordStatus::from_http_status(status), RecordStatus::Spent);
+        }
+        assert_eq!(RecordStatus::from_http_status(404), RecordStatus::Unspent);
+
+        // Anything else says nothing about the record.
+        for status in [0, 199, 300, 304, 400, 403, 429, 500, 503] {
+            assert_eq!(
+                RecordStatus::from_http_status(status),
+                RecordStatus::Unknown
+            );
+        }
     }
 }
 
@@ -298,7 +657,7 @@ mod tests {
 
     #[wasm_bindgen_test]
     async fn test_request_records_internal() {
-        use crate::records::request_records_internal;
+        use crate::records::{request_records_internal, BlockFetch, SpentCheck};
         use crate::CurrentNetwork;
         match request_records_internal::<CurrentNetwork>(
             None,
@@ -307,12 +666,16 @@ mod tests {
             Some(82900),
             None,
             "http://115.231.235.242:33030".to_string(),
+            SpentCheck::Remote,
+            BlockFetch::default(),
+            None,
+            None,
         )
         .await
         {
             Ok(records) => {
                 for r in records {
-                    console_log!("{}", r)
+                    console_log!("{} ({})", r.plaintext(), r.status())
                 }
             }
             Err(e) => {
@@ -0,0 +1,514 @@
+use crate::error::WalletError;
+use crate::records::RecordStatus;
+use crate::transfer::{
+    build_execution, credits_locator, handle_transaction, transfer_inputs, TransactionOutput,
+};
+use crate::utils::{
+    ensure_not_aborted, get_request, parse_private_key, parse_record, record_microcredits, sleep,
+};
+use anyhow::{bail, ensure};
+use js_sys::{Array, Reflect};
+use snarkvm_console_account::{PrivateKey, ViewKey};
+use snarkvm_console_program::{Network, Plaintext, Record, Value};
+use snarkvm_synthesizer::Transaction;
+use wasm_bindgen::prelude::*;
+use web_sys::AbortSignal;
+
+/// The time between two checks whether a join has been confirmed.
+const CONFIRMATION_POLL_INTERVAL_MS: i32 = 5_000;
+
+/// The number of checks before giving up on a join being confirmed.
+const MAX_CONFIRMATION_POLLS: usize = 120;
+
+/// How `send` picks the records to spend.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionStrategy {
+    /// Spend the smallest record which covers the amount. If there is none, join the largest
+    /// records, so that as few records as possible have to be joined.
+    #[default]
+    SmallestSufficient,
+    /// Spend the largest records first.
+    LargestFirst,
+    /// Spend the smallest records first, which consolidates small records over time.
+    SmallestFirst,
+}
+
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct SendOutput {
+    records: Vec<String>,
+    fee_record: Option<String>,
+    joins: Vec<String>,
+    transaction: TransactionOutput,
+}
+
+#[wasm_bindgen]
+impl SendOutput {
+    /// The records spent on the amount.
+    #[wasm_bindgen(getter)]
+    pub fn records(&self) -> Array {
+        self.records
+            .iter()
+            .map(|record| JsValue::from_str(record))
+            .collect()
+    }
+
+    /// The record which paid the fees of the joins and the transfer, if there was a fee.
+    #[wasm_bindgen(getter)]
+    pub fn fee_record(&self) -> Option<String> {
+        self.fee_record.clone()
+    }
+
+    /// The ids of the join transactions which merged the records before the transfer.
+    #[wasm_bindgen(getter)]
+    pub fn joins(&self) -> Array {
+        self.joins
+            .iter()
+            .map(|join| JsValue::from_str(join))
+            .collect()
+    }
+
+    /// The transfer transaction.
+    #[wasm_bindgen(getter)]
+    pub fn transaction(&self) -> TransactionOutput {
+        self.transaction.clone()
+    }
+}
+
+/// Reads the records passed to `send`, which are either plaintexts or `OwnedRecord`s returned by a
+/// scan. `OwnedRecord`s which are not known to be unspent are left out.
+pub(crate) fn spendable_records(records: &Array) -> anyhow::Result<Vec<String>> {
+    let mut plaintexts = Vec::new();
+    for record in records.iter() {
+        if let Some(plaintext) = record.as_string() {
+            plaintexts.push(plaintext);
+            continue;
+        }
+
+        let property = |name: &str| {
+            Reflect::get(&record, &JsValue::from_str(name))
+                .ok()
+                .and_then(|value| value.as_string())
+        };
+        match (property("plaintext"), property("status")) {
+            (Some(plaintext), Some(status)) => {
+                if status == RecordStatus::Unspent.as_str() {
+                    plaintexts.push(plaintext);
+                }
+            }
+            _ => bail!(WalletError::InvalidRecord(
+                "Expected a record plaintext or an OwnedRecord".to_string()
+            )),
+        }
+    }
+    Ok(plaintexts)
+}
+
+/// Transfers the amount to the recipient, picking the records to spend from the given ones.
+/// If no single record covers the amount, the picked records are joined first, which requires
+/// each join to be broadcast and confirmed before the transfer can spend its output. The fee
+/// record pays the fee of every join and then of the transfer, each time spending the change
+/// left by the previous fee.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn send_internal<N: Network>(
+    private_key: String,
+    records: Vec<String>,
+    amount: u64,
+    recipient: String,
+    fee: Option<u64>,
+    strategy: SelectionStrategy,
+    query_endpoint: String,
+    broadcast: Option<String>,
+    signal: Option<AbortSignal>,
+) -> anyhow::Result<SendOutput> {
+    let private_key = parse_private_key::<N>(&private_key)?;
+    let signal = signal.as_ref();
+
+    let records = records
+        .iter()
+        .map(|record| parse_record::<N>(record))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let balances = records.iter().map(record_microcredits).collect::<Vec<_>>();
+
+    // Pick the records for the amount, and a separate one for the fees.
+    let fee_amount = fee.unwrap_or(0);
+    let (selected, fee_index) = select_records_with_fee(&balances, amount, fee_amount, strategy)?;
+
+    let mut output = SendOutput {
+        records: selected.iter().map(|i| records[*i].to_string()).collect(),
+        fee_record: fee_index.map(|i| records[i].to_string()),
+        joins: Vec::new(),
+        transaction: TransactionOutput::default(),
+    };
+
+    // Join the picked records into one, unless a single record covers the amount.
+    let mut record = records[selected[0]].clone();
+    let mut fee_record = fee_index.map(|i| records[i].clone());
+    if selected.len() > 1 {
+        let endpoint = match &broadcast {
+            Some(endpoint) => endpoint.clone(),
+            None => bail!(WalletError::InvalidInput(
+                "Joining records requires a broadcast endpoint".to_string()
+            )),
+        };
+        for index in &selected[1..] {
+            let join = join_records(
+                &private_key,
+                record,
+                records[*index].clone(),
+                fee_record.map(|fee_record| (fee_record, fee_amount)),
+                &query_endpoint,
+                &endpoint,
+                signal,
+            )
+            .await?;
+            output.joins.push(join.id);
+            record = join.joined;
+            fee_record = join.change;
+        }
+    }
+
+    // Transfer the amount.
+    ensure_not_aborted(signal)?;
+    let locator = credits_locator::<N>("transfer")?;
+    let inputs = transfer_inputs::<N>(&record.to_string(), amount, &recipient)?;
+    let fee = fee_record.map(|fee_record| (fee_record, fee_amount));
+    let transaction = build_execution(&private_key, &locator, &inputs, fee, &query_endpoint)?;
+    let build = broadcast.is_none();
+    output.transaction = handle_transaction(
+        broadcast,
+        build,
+        build,
+        transaction,
+        locator.to_string(),
+        signal,
+    )
+    .await?;
+
+    Ok(output)
+}
+
+/// A confirmed join of two records.
+struct Join<N: Network> {
+    id: String,
+    joined: Record<N, Plaintext<N>>,
+    /// The change left by the fee, if the join paid one.
+    change: Option<Record<N, Plaintext<N>>>,
+}
+
+/// Joins the two records, paying the fee if one is given, and waits until the join is confirmed.
+async fn join_records<N: Network>(
+    private_key: &PrivateKey<N>,
+    first: Record<N, Plaintext<N>>,
+    second: Record<N, Plaintext<N>>,
+    fee: Option<(Record<N, Plaintext<N>>, u64)>,
+    query_endpoint: &str,
+    broadcast: &str,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<Join<N>> {
+    ensure_not_aborted(signal)?;
+    let view_key = ViewKey::try_from(private_key)?;
+    let locator = credits_locator::<N>("join")?;
+    let inputs = vec![Value::Record(first), Value::Record(second)];
+    let has_fee = fee.is_some();
+    let transaction = build_execution(private_key, &locator, &inputs, fee, query_endpoint)?;
+
+    // The join outputs the joined record, and the fee outputs the change.
+    let joined = transition_record(&transaction, &view_key, "join")?;
+    let change = if has_fee {
+        Some(transition_record(&transaction, &view_key, "fee")?)
+    } else {
+        None
+    };
+
+    let output = handle_transaction(
+        Some(broadcast.to_string()),
+        false,
+        false,
+        transaction,
+        locator.to_string(),
+        signal,
+    )
+    .await?;
+    wait_for_confirmation(query_endpoint, &output.id(), signal).await?;
+    Ok(Join {
+        id: output.id(),
+        joined,
+        change,
+    })
+}
+
+/// Decrypts the record output by the transition of the given `credits.aleo` function.
+fn transition_record<N: Network>(
+    transaction: &Transaction<N>,
+    view_key: &ViewKey<N>,
+    function: &str,
+) -> anyhow::Result<Record<N, Plaintext<N>>> {
+    let record = transaction
+        .transitions()
+        .filter(|transition| transition.function_name().to_string() == function)
+        .flat_map(|transition| transition.records())
+        .find(|(_, record)| record.is_owner(view_key))
+        .ok_or_else(|| WalletError::Internal(format!("The {function} did not output a record")))?;
+    Ok(record.1.decrypt(view_key)?)
+}
+
+/// Polls the endpoint until it knows the transaction.
+async fn wait_for_confirmation(
+    endpoint: &str,
+    transaction_id: &str,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<()> {
+    let endpoint = format!("{endpoint}/testnet3/transaction/{transaction_id}");
+    for _ in 0..MAX_CONFIRMATION_POLLS {
+        sleep(CONFIRMATION_POLL_INTERVAL_MS).await?;
+        ensure_not_aborted(signal)?;
+
+        // Failed requests are retried, as the node may be briefly unreachable.
+        if let Ok(response) = get_request(&endpoint, signal).await {
+            if response.ok() {
+                return Ok(());
+            }
+        }
+    }
+    bail!(WalletError::Network {
+        status: None,
+        message: format!("The transaction {transaction_id} was not confirmed in time"),
+    })
+}
+
+/// Picks the records to spend on the amount, returning their indices.
+pub(crate) fn select_records(
+    balances: &[u64],
+    amount: u64,
+    strategy: SelectionStrategy,
+) -> anyhow::Result<Vec<usize>> {
+    ensure!(
+        !balances.is_empty(),
+        WalletError::InvalidRecord("No records to spend".to_string())
+    );
+
+    let mut indices = (0..balances.len()).collect::<Vec<_>>();
+    match strategy {
+        SelectionStrategy::SmallestSufficient => {
+            let smallest = indices
+                .iter()
+                .copied()
+                .filter(|i| balances[*i] >= amount)
+                .min_by_key(|i| balances[*i]);
+            if let Some(index) = smallest {
+                return Ok(vec![index]);
+            }
+            indices.sort_by_key(|i| std::cmp::Reverse(balances[*i]));
+        }
+        SelectionStrategy::LargestFirst => indices.sort_by_key(|i| std::cmp::Reverse(balances[*i])),
+        SelectionStrategy::SmallestFirst => indices.sort_by_key(|i| balances[*i]),
+    }
+
+    // Take records in order until they cover the amount.
+    let mut selected = Vec::new();
+    let mut total = 0u128;
+    for index in indices {
+        selected.push(index);
+        total += u128::from(balances[index]);
+        if total >= u128::from(amount) {
+            return Ok(selected);
+        }
+    }
+    bail!(WalletError::InvalidRecord(format!(
+        "The records hold {total} microcredits, which cannot cover {amount} microcredits"
+    )))
+}
+
+/// Picks the records to spend on the amount along with a separate record for the fees, if there
+/// is a fee, returning their indices. Every join of the picked records pays the fee as well, so
+/// the fee record has to cover it once per picked record.
+pub(crate) fn select_records_with_fee(
+    balances: &[u64],
+    amount: u64,
+    fee: u64,
+    strategy: SelectionStrategy,
+) -> anyhow::Result<(Vec<usize>, Option<usize>)> {
+    let selected = select_records(balances, amount, strategy)?;
+    if fee == 0 {
+        return Ok((selected, None));
+    }
+    if let Some(fee_index) = total_fee(fee, &selected)
+        .and_then(|total_fee| select_fee_record(balances, &selected, total_fee))
+    {
+        return Ok((selected, Some(fee_index)));
+    }
+
+    // The picked records leave none for the fees. Set aside each record which covers the fee in
+    // turn, smallest first, and pick the records for the amount from the others.
+    let mut fee_indices = (0..balances.len())
+        .filter(|i| balances[*i] >= fee)
+        .collect::<Vec<_>>();
+    fee_indices.sort_by_key(|i| balances[*i]);
+    for fee_index in fee_indices {
+        let others = (0..balances.len())
+            .filter(|i| *i != fee_index)
+            .collect::<Vec<_>>();
+        let other_balances = others.iter().map(|i| balances[*i]).collect::<Vec<_>>();
+        if let Ok(selected) = select_records(&other_balances, amount, strategy) {
+            let selected = selected.into_iter().map(|i| others[i]).collect::<Vec<_>>();
+            if total_fee(fee, &selected).is_some_and(|total_fee| balances[fee_index] >= total_fee) {
+                return Ok((selected, Some(fee_index)));
+            }
+        }
+    }
+    bail!(WalletError::InvalidRecord(format!(
+        "No other record can pay the fee of {fee} microcredits for the transfer and every join"
+    )))
+}
+
+/// The fees paid for spending the selected records: one for each join, and one for the transfer.
+fn total_fee(fee: u64, selected: &[usize]) -> Option<u64> {
+    fee.checked_mul(selected.len() as u64)
+}
+
+/// Picks the smallest record outside of the selection which covers the fee.
+fn select_fee_record(balances: &[u64], selected: &[usize], fee: u64) -> Option<usize> {
+    (0..balances.len())
+        .filter(|i| !selected.contains(i) && balances[*i] >= fee)
+        .min_by_key(|i| balances[*i])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_fee_record, select_records, select_records_with_fee, SelectionStrategy};
+    use crate::error::WalletError;
+
+    #[test]
+    fn test_select_records() {
+        let balances = [50, 200, 120, 10, 500];
+
+        // The smallest sufficient record is preferred.
+        let select = |amount, strategy| select_records(&balances, amount, strategy).unwrap();
+        assert_eq!(select(100, SelectionStrategy::SmallestSufficient), vec![2]);
+        assert_eq!(select(120, SelectionStrategy::SmallestSufficient), vec![2]);
+        assert_eq!(select(121, SelectionStrategy::SmallestSufficient), vec![1]);
+        assert_eq!(select(0, SelectionStrategy::SmallestSufficient), vec![3]);
+
+        // Without one, the largest records are joined.
+        assert_eq!(
+            select(600, SelectionStrategy::SmallestSufficient),
+            vec![4, 1]
+        );
+        assert_eq!(
+            select(880, SelectionStrategy::SmallestSufficient),
+            vec![4, 1, 2, 0, 3]
+        );
+
+        assert_eq!(select(100, SelectionStrategy::LargestFirst), vec![4]);
+        assert_eq!(select(750, SelectionStrategy::LargestFirst), vec![4, 1, 2]);
+
+        assert_eq!(select(50, SelectionStrategy::SmallestFirst), vec![3, 0]);
+        assert_eq!(select(60, SelectionStrategy::SmallestFirst), vec![3, 0]);
+        assert_eq!(select(61, SelectionStrategy::SmallestFirst), vec![3, 0, 2]);
+    }
+
+    #[test]
+    fn test_select_records_insufficient() {
+        for strategy in [
+            SelectionStrategy::SmallestSufficient,
+            SelectionStrategy::LargestFirst,
+            SelectionStrategy::SmallestFirst,
+        ] {
+            let error = WalletError::from(select_records(&[50, 200], 251, strategy).unwrap_err());
+            assert_eq!(error.code(), "INVALID_RECORD");
+            let error = WalletError::from(select_records(&[], 0, strategy).unwrap_err());
+            assert_eq!(error.code(), "INVALID_RECORD");
+        }
+
+        // The sum of the records must not overflow.
+        assert_eq!(
+            select_records(
+                &[u64::MAX, u64::MAX],
+                u64::MAX,
+                SelectionStrategy::SmallestFirst
+            )
+            .unwrap(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn test_select_fee_record() {
+        let balances = [50, 200, 120, 10, 500];
+        assert_eq!(select_fee_record(&balances, &[2], 10), Some(3));
+        assert_eq!(select_fee_record(&balances, &[3], 10), Some(0));
+        assert_eq!(select_fee_record(&balances, &[4], 300), None);
+        assert_eq!(select_fee_record(&balances, &[0, 1, 2, 3, 4], 1), None);
+    }
+
+    #[test]
+    fn test_select_records_with_fee() {
+        let select = |balances: &[u64], amount, fee, strategy| {
+            select_records_with_fee(balances, amount, fee, strategy).unwrap()
+        };
+
+        // Without a fee, no fee record is picked.
+        assert_eq!(
+            select(&[100, 60], 50, 0, SelectionStrategy::LargestFirst),
+            (vec![0], None)
+        );
+        assert_eq!(
+            select(&[100, 60], 50, 30, SelectionStrategy::LargestFirst),
+            (vec![0], Some(1))
+        );
+
+        // If the records picked for the amount leave none for the fee, the fee record is
+        // picked first.
+        assert_eq!(
+            select(&[100, 60], 50, 70, SelectionStrategy::LargestFirst),
+            (vec![1], Some(0))
+        );
+        assert_eq!(
+            select(&[100, 60], 50, 70, SelectionStrategy::SmallestFirst),
+            (vec![1], Some(0))
+        );
+        assert_eq!(
+            select(
+                &[40, 30, 90, 80],
+                60,
+                85,
+                SelectionStrategy::SmallestSufficient
+            ),
+            (vec![3], Some(2))
+        );
+        assert_eq!(
+            select(&[40, 30, 90], 60, 45, SelectionStrategy::SmallestSufficient),
+            (vec![0, 1], Some(2))
+        );
+
+        // The fee record pays for the join as well as for the transfer.
+        assert_eq!(
+            select(&[40, 30, 25], 70, 10, SelectionStrategy::LargestFirst),
+            (vec![0, 1], Some(2))
+        );
+        assert_eq!(
+            select(&[40, 30, 50, 20], 70, 10, SelectionStrategy::LargestFirst),
+            (vec![2, 0], Some(3))
+        );
+
+        // No record covers the fee, or the fee of every join.
+        for (balances, amount, fee, strategy) in [
+            (&[100, 60][..], 50, 101, SelectionStrategy::LargestFirst),
+            (&[100, 60][..], 70, 70, SelectionStrategy::LargestFirst),
+            (&[40, 30, 15][..], 70, 10, SelectionStrategy::LargestFirst),
+            (
+                &[40, 30, 90][..],
+                60,
+                46,
+                SelectionStrategy::SmallestSufficient,
+            ),
+        ] {
+            let error = WalletError::from(
+                select_records_with_fee(balances, amount, fee, strategy).unwrap_err(),
+            );
+            assert_eq!(error.code(), "INVALID_RECORD");
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use crate::error::WalletError;
+use crate::records::{
+    fetch_block, fetch_latest_height, record_statuses, scan_blocks, BlockFetch, BlockScan,
+    ChainMismatch, RecordStatus, ScanProgress, SerialNumberLookup, SpentCheck, MAX_REORG_DEPTH,
+};
+use crate::utils::{parse_account, parse_record};
+use serde::{Deserialize, Serialize};
+use snarkvm_console_account::{PrivateKey, ViewKey};
+use snarkvm_console_program::{Field, Network, Plaintext, Record};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// The number of times a sync restarts after the chain changed underneath it.
+const MAX_SYNC_ATTEMPTS: usize = 3;
+
+/// The persistent state of an incremental record scan.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct ScanState {
+    /// The height of the last scanned block, or `None` if nothing was scanned yet.
+    pub(crate) height: Option<u32>,
+    /// The hashes of the most recently scanned blocks, keyed by height.
+    pub(crate) block_hashes: BTreeMap<u32, String>,
+    /// The records found so far which are not known to be spent.
+    pub(crate) records: Vec<ScannedRecord>,
+    /// The records spent within the last `MAX_REORG_DEPTH` blocks, kept so that they can be
+    /// restored if their spend is rolled back by a reorg.
+    pub(crate) spent_records: Vec<ScannedRecord>,
+}
+
+/// An owned record tracked by the scan state.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ScannedRecord {
+    pub(crate) commitment: String,
+    pub(crate) record: String,
+    /// The height of the block the record was created in.
+    pub(crate) height: u32,
+    pub(crate) status: RecordStatus,
+    /// The height by which the record was spent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) spent_at: Option<u32>,
+}
+
+impl ScanState {
+    pub(crate) fn from_json(state: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(state)
+            .map_err(|e| WalletError::Parse(format!("scan state: {e}")))?)
+    }
+
+    pub(crate) fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Rolls the state back to the given common ancestor, or to an empty state if there is none.
+    /// Returns the number of blocks rolled back.
+    fn roll_back(&mut self, ancestor: Option<u32>) -> u32 {
+        let height = match self.height {
+            Some(height) => height,
+            None => return 0,
+        };
+        let ancestor = match ancestor {
+            Some(ancestor) => ancestor,
+            None => {
+                *self = ScanState::default();
+                return height.saturating_add(1);
+            }
+        };
+
+        self.height = Some(ancestor);
+        self.block_hashes.retain(|height, _| *height <= ancestor);
+        self.records.retain(|record| record.height <= ancestor);
+
+        // Restore the records whose spend may have been rolled back, so that they are checked again.
+        let (restored, spent_records): (Vec<_>, Vec<_>) = std::mem::take(&mut self.spent_records)
+            .into_iter()
+            .filter(|record| record.height <= ancestor)
+            .partition(|record| record.spent_at.is_none_or(|spent_at| spent_at > ancestor));
+        self.spent_records = spent_records;
+        self.records
+            .extend(restored.into_iter().map(|record| ScannedRecord {
+                status: RecordStatus::Unknown,
+                spent_at: None,
+                ..record
+            }));
+
+        height - ancestor
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct SyncOutput {
+    state: String,
+    reorg_depth: u32,
+}
+
+#[wasm_bindgen]
+impl SyncOutput {
+    /// The updated scan state as JSON.
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> String {
+        self.state.clone()
+    }
+
+    /// The number of blocks rolled back because of a reorg, or 0 if there was none.
+    #[wasm_bindgen(getter)]
+    pub fn reorg_depth(&self) -> u32 {
+        self.reorg_depth
+    }
+}
+
+/// Continues scanning from the given state up to the latest block, returning the updated state.
+pub(crate) async fn sync_internal<N: Network>(
+    private_key: Option<String>,
+    view_key: String,
+    state: Option<String>,
+    endpoint: String,
+    spent_check: SpentCheck,
+    fetch: BlockFetch,
+) -> anyhow::Result<SyncOutput> {
+    // Derive the view key and optional private key.
+    let (private_key, view_key) = parse_account::<N>(private_key, view_key)?;
+
+    let mut state = match state {
+        Some(state) => ScanState::from_json(&state)?,
+        None => ScanState::default(),
+    };
+
+    let mut reorg_depth = 0;
+    let mut attempts = 0;
+    loop {
+        // Find the range of blocks which have not been scanned yet.
+        let latest_height =
+            fetch_latest_height(&format!("{}/testnet3/latest/height", endpoint), None).await?;
+        let start_height = state.height.map_or(0, |height| height.saturating_add(1));
+        if start_height > latest_height {
+            // There are no new blocks, but the scanned tip may have been replaced by a reorg.
+            let tip = state
+                .height
+                .and_then(|height| state.block_hashes.get(&height).map(|hash| (height, hash)));
+            let replaced = match tip {
+                Some((height, _)) if height > latest_height => true,
+                Some((height, hash)) => {
+                    fetch_block::<N>(&endpoint, height)
+                        .await?
+                        .hash()
+                        .to_string()
+                        != *hash
+                }
+                None => false,
+            };
+            if replaced {
+                let ancestor = find_common_ancestor::<N>(&endpoint, &state, latest_height).await?;
+                reorg_depth += state.roll_back(ancestor);
+                continue;
+            }
+            break;
+        }
+
+        let previous_hash = match state
+            .height
+            .and_then(|height| state.block_hashes.get(&height))
+        {
+            Some(hash) => Some(N::BlockHash::from_str(hash).map_err(|_| {
+                WalletError::Parse(format!("scan state: invalid block hash '{hash}'"))
+            })?),
+            None => None,
+        };
+
+        match scan_blocks(
+            &view_key,
+            &endpoint,
+            start_height,
+            latest_height,
+            previous_hash,
+            spent_check,
+            fetch,
+            ScanProgress::none(),
+            None,
+        )
+        .await
+        {
+            Ok(scan) => {
+                update_state(
+                    &mut state,
+                    private_key,
+                    &view_key,
+                    &endpoint,
+                    spent_check,
+                    scan,
+                    latest_height,
+                )
+                .await?;
+                break;
+            }
+            Err(error) => {
+                let mismatch_height = match error.downcast_ref::<ChainMismatch>() {
+                    Some(mismatch) => mismatch.height,
+                    None => return Err(error),
+                };
+                attempts += 1;
+                if attempts >= MAX_SYNC_ATTEMPTS {
+                    return Err(error);
+                }
+
+                // If the first new block does not extend the scanned chain, that chain is no
+                // longer canonical. Otherwise the chain changed during the scan; scan again.
+                if mismatch_height == start_height {
+                    let ancestor =
+                        find_common_ancestor::<N>(&endpoint, &state, latest_height).await?;
+                    reorg_depth += state.roll_back(ancestor);
+                }
+            }
+        }
+    }
+
+    Ok(SyncOutput {
+        state: state.to_json()?,
+        reorg_depth,
+    })
+}
+
+/// Walks back the stored block hashes until one of them still matches the chain on the node.
+/// Blocks above the latest height no longer exist, so they never match.
+async fn find_common_ancestor<N: Network>(
+    endpoint: &str,
+    state: &ScanState,
+    latest_height: u32,
+) -> anyhow::Result<Option<u32>> {
+    for (height, hash) in state.block_hashes.range(..=latest_height).rev() {
+        let block = fetch_block::<N>(endpoint, *height).await?;
+        if block.hash().to_string() == *hash {
+            return Ok(Some(*height));
+        }
+    }
+    Ok(None)
+}
+
+/// Adds the results of scanning up to `latest_height` to the state.
+async fn update_state<N: Network>(
+    state: &mut ScanState,
+    private_key: Option<PrivateKey<N>>,
+    view_key: &ViewKey<N>,
+    endpoint: &str,
+    spent_check: SpentCheck,
+    scan: BlockScan<N>,
+    latest_height: u32,
+) -> anyhow::Result<()> {
+    // Combine the known records with the records found in the new blocks.
+    let mut records: Vec<(u32, Field<N>, Record<N, Plaintext<N>>)> = Vec::new();
+    for known in &state.records {
+        let commitment = Field::<N>::from_str(&known.commitment)
+            .map_err(|e| WalletError::Parse(format!("commitment: {e}")))?;
+        records.push((known.height, commitment, parse_record::<N>(&known.record)?));
+    }
+    for owned in &scan.owned_records {
+        records.push((
+            owned.height,
+            owned.commitment,
+            owned.record.decrypt(view_key)?,
+        ));
+    }
+
+    // Check which of the records are spent. Known records were unspent as of the previous
+    // scan, so for the local check the new blocks are the only place they can be spent in.
+    let commitments = records
+        .iter()
+        .map(|(_, commitment, _)| *commitment)
+        .collect::<Vec<_>>();
+    let lookup = match spent_check {
+        SpentCheck::Remote => SerialNumberLookup::Remote(endpoint),
+        // The scan always runs up to the latest height.
+        SpentCheck::Local => SerialNumberLookup::Local {
+            spent_serial_numbers: &scan.spent_serial_numbers,
+            scanned_to_tip: true,
+        },
+    };
+    let statuses = record_statuses(private_key, lookup, &commitments, None).await?;
+
+    state.records.clear();
+    for ((height, commitment, record), status) in records.into_iter().zip(statuses) {
+        let mut scanned = ScannedRecord {
+            commitment: commitment.to_string(),
+            record: record.to_string(),
+            height,
+            status,
+            spent_at: None,
+        };
+        if status == RecordStatus::Spent {
+            // The local check knows the exact height of the spend; otherwise it happened
+            // somewhere before the latest height.
+            let spent_at = private_key
+                .and_then(|private_key| {
+                    Record::<N, Plaintext<N>>::serial_number(private_key, commitment).ok()
+                })
+                .and_then(|serial_number| scan.spent_serial_numbers.get(&serial_number).copied());
+            scanned.spent_at = Some(spent_at.unwrap_or(latest_height));
+            state.spent_records.push(scanned);
+        } else {
+            state.records.push(scanned);
+        }
+    }
+
+    // Only keep what is needed to roll back a reorg of up to `MAX_REORG_DEPTH` blocks.
+    let oldest_height = latest_height.saturating_sub(MAX_REORG_DEPTH as u32);
+    state.spent_records.retain(|record| {
+        record
+            .spent_at
+            .is_some_and(|spent_at| spent_at > oldest_height)
+    });
+    state.height = Some(latest_height);
+    state.block_hashes.extend(
+        scan.block_hashes
+            .into_iter()
+            .map(|(height, hash)| (height, hash.to_string())),
+    );
+    state
+        .block_hashes
+        .retain(|height, _| *height > oldest_height);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScanState, ScannedRecord};
+    use crate::records::RecordStatus;
+
+    fn scanned_record(height: u32, spent_at: Option<u32>) -> ScannedRecord {
+        ScannedRecord {
+            commitment: format!("{height}field"),
+            record: "{ owner: aleo1.private }".to_string(),
+            height,
+            status: match spent_at {
+                Some(_) => RecordStatus::Spent,
+                None => RecordStatus::Unspent,
+            },
+            spent_at,
+        }
+    }
+
+    #[test]
+    fn test_scan_state_json() {
+        let state = ScanState {
+            height: Some(82900),
+            block_hashes: [(82900, "ab1block".to_string())].into_iter().collect(),
+            records: vec![scanned_record(82870, None)],
+            spent_records: vec![scanned_record(82880, Some(82890))],
+        };
+        let json = state.to_json().unwrap();
+        assert!(json.contains("\"status\":\"unspent\""));
+        assert_eq!(ScanState::from_json(&json).unwrap(), state);
+
+        assert_eq!(ScanState::from_json("{}").unwrap(), ScanState::default());
+        assert!(ScanState::from_json("not a state").is_err());
+    }
+
+    #[test]
+    fn test_scan_state_roll_back() {
+        let mut state = ScanState {
+            height: Some(100),
+            block_hashes: (90..=100)
+                .map(|height| (height, format!("ab1{height}")))
+                .collect(),
+            records: vec![scanned_record(80, None), scanned_record(97, None)],
+            spent_records: vec![
+                scanned_record(85, Some(92)),
+                scanned_record(86, Some(98)),
+                scanned_record(99, Some(100)),
+            ],
+        };
+
+        assert_eq!(state.roll_back(Some(95)), 5);
+        assert_eq!(state.height, Some(95));
+        assert_eq!(state.block_hashes.keys().last(), Some(&95));
+
+        // The record created after the ancestor is dropped, and the record spent after it is restored.
+        let heights = state.records.iter().map(|r| r.height).collect::<Vec<_>>();
+        assert_eq!(heights, vec![80, 86]);
+        assert_eq!(state.records[1].status, RecordStatus::Unknown);
+        assert_eq!(state.records[1].spent_at, None);
+        assert_eq!(state.spent_records, vec![scanned_record(85, Some(92))]);
+
+        // Without a common ancestor, everything is rescanned.
+        assert_eq!(state.roll_back(None), 96);
+        assert_eq!(state, ScanState::default());
+    }
+}
@@ -5,125 +5,463 @@
  *
  * This file is part of aleo-wallet-test.
  */
-use crate::utils::post_request;
+use crate::broadcast::ensure_verified;
+use crate::context::{verify_transactions, wallet_context};
+use crate::error::WalletError;
+use crate::program::load_program;
+use crate::utils::{
+    ensure_not_aborted, parse_address, parse_private_key, parse_record, post_request,
+    record_microcredits, response_text,
+};
 use anyhow::{bail, ensure};
+use js_sys::Uint8Array;
 use serde_json::from_str;
-use snarkvm_console_account::address::Address;
 use snarkvm_console_account::PrivateKey;
 use snarkvm_console_network::Network;
 use snarkvm_console_program::{Identifier, Locator, Plaintext, ProgramID, Record, Value};
-use snarkvm_synthesizer::{ConsensusMemory, ConsensusStore, Query, Transaction, VM};
+use snarkvm_synthesizer::{Query, Transaction};
 use snarkvm_utilities::ToBytes;
-use std::path::PathBuf;
 use std::str::FromStr;
-use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen::prelude::*;
+use web_sys::AbortSignal;
 
 pub const CREDITS_PROVING_KEYS_T: &[u8] = include_bytes!("../credits_proving_keys");
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn transfer_internal<N: Network>(
     private_key: String,
     record: String,
     amount: u64,
     recipient: String,
+    fee_record: Option<String>,
+    fee: Option<u64>,
     query_endpoint: String,
-    broadcast: String,
-) -> anyhow::Result<String> {
-    let record = Record::<N, Plaintext<N>>::from_str(&record)?;
-    let recipient = Address::<N>::from_str(&recipient)?;
+    broadcast: Option<String>,
+    signal: Option<AbortSignal>,
+) -> anyhow::Result<TransactionOutput> {
+    let inputs = transfer_inputs::<N>(&record, amount, &recipient)?;
 
-    // Specify the query
-    let query = Query::from(&query_endpoint);
+    execute_credits_internal(
+        private_key,
+        "transfer",
+        inputs,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        signal,
+    )
+    .await
+}
+
+/// Prepares the inputs for a `credits.aleo/transfer` execution.
+pub(crate) fn transfer_inputs<N: Network>(
+    record: &str,
+    amount: u64,
+    recipient: &str,
+) -> anyhow::Result<Vec<Value<N>>> {
+    let record = parse_record::<N>(record)?;
+    let recipient = parse_address::<N>(recipient)?;
+
+    Ok(vec![
+        Value::Record(record),
+        Value::from_str(&format!("{}", recipient))?,
+        Value::from_str(&format!("{}u64", amount))?,
+    ])
+}
+
+pub(crate) async fn split_internal<N: Network>(
+    private_key: String,
+    record: String,
+    amount: u64,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    let record = parse_record::<N>(&record)?;
+    ensure!(
+        record_microcredits(&record) >= amount,
+        WalletError::InvalidRecord(format!(
+            "The record cannot be split into {amount} microcredits"
+        ))
+    );
+
+    // Prepare the inputs for a split.
+    let inputs = vec![
+        Value::Record(record),
+        Value::from_str(&format!("{}u64", amount))?,
+    ];
+
+    execute_credits_internal(
+        private_key,
+        "split",
+        inputs,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        None,
+    )
+    .await
+}
+
+pub(crate) async fn join_internal<N: Network>(
+    private_key: String,
+    first_record: String,
+    second_record: String,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    // Prepare the inputs for a join.
+    let inputs = vec![
+        Value::Record(parse_record::<N>(&first_record)?),
+        Value::Record(parse_record::<N>(&second_record)?),
+    ];
+
+    execute_credits_internal(
+        private_key,
+        "join",
+        inputs,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        None,
+    )
+    .await
+}
+
+pub(crate) async fn mint_internal<N: Network>(
+    private_key: String,
+    recipient: String,
+    amount: u64,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    let recipient = parse_address::<N>(&recipient)?;
+
+    // Prepare the inputs for a mint. The network rejects it unless the caller is permitted to mint.
+    let inputs = vec![
+        Value::from_str(&format!("{}", recipient))?,
+        Value::from_str(&format!("{}u64", amount))?,
+    ];
+
+    execute_credits_internal(
+        private_key,
+        "mint",
+        inputs,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        None,
+    )
+    .await
+}
+
+pub(crate) async fn fee_internal<N: Network>(
+    private_key: String,
+    record: String,
+    amount: u64,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    let record = parse_record::<N>(&record)?;
+    ensure!(
+        record_microcredits(&record) >= amount,
+        WalletError::InvalidRecord(format!(
+            "The record cannot pay a fee of {amount} microcredits"
+        ))
+    );
+
+    // Prepare the inputs for a fee.
+    let inputs = vec![
+        Value::Record(record),
+        Value::from_str(&format!("{}u64", amount))?,
+    ];
+
+    execute_credits_internal(
+        private_key,
+        "fee",
+        inputs,
+        None,
+        None,
+        query_endpoint,
+        broadcast,
+        None,
+    )
+    .await
+}
+
+/// Proves and broadcasts an execution of the given `credits.aleo` function.
+#[allow(clippy::too_many_arguments)]
+async fn execute_credits_internal<N: Network>(
+    private_key: String,
+    function: &str,
+    inputs: Vec<Value<N>>,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+    signal: Option<AbortSignal>,
+) -> anyhow::Result<TransactionOutput> {
+    let locator = credits_locator::<N>(function)?;
+
+    execute_internal(
+        private_key,
+        locator,
+        inputs,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        signal,
+    )
+    .await
+}
+
+/// Returns the locator of the given `credits.aleo` function.
+pub(crate) fn credits_locator<N: Network>(function: &str) -> anyhow::Result<Locator<N>> {
+    Ok(Locator::<N>::new(
+        ProgramID::from_str("credits.aleo")?,
+        Identifier::from_str(function)?,
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn execute_program_internal<N: Network>(
+    private_key: String,
+    program_id: String,
+    function: String,
+    inputs: Vec<String>,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+) -> anyhow::Result<TransactionOutput> {
+    let locator = Locator::<N>::new(
+        ProgramID::from_str(&program_id)
+            .map_err(|e| WalletError::InvalidInput(format!("program id: {e}")))?,
+        Identifier::from_str(&function)
+            .map_err(|e| WalletError::InvalidInput(format!("function: {e}")))?,
+    );
+
+    // Parse the inputs.
+    let inputs = inputs
+        .iter()
+        .map(|input| {
+            Value::<N>::from_str(input)
+                .map_err(|e| WalletError::Parse(format!("input '{input}': {e}")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Add the program and its imports to the VM.
+    let context = wallet_context::<N>()?;
+    load_program(context.vm(), &query_endpoint, locator.program_id()).await?;
+
+    execute_internal(
+        private_key,
+        locator,
+        inputs,
+        fee_record,
+        fee,
+        query_endpoint,
+        broadcast,
+        None,
+    )
+    .await
+}
+
+/// Proves and broadcasts an execution of the function at the given locator.
+#[allow(clippy::too_many_arguments)]
+async fn execute_internal<N: Network>(
+    private_key: String,
+    locator: Locator<N>,
+    inputs: Vec<Value<N>>,
+    fee_record: Option<String>,
+    fee: Option<u64>,
+    query_endpoint: String,
+    broadcast: Option<String>,
+    signal: Option<AbortSignal>,
+) -> anyhow::Result<TransactionOutput> {
+    // Prepare the fees.
+    let fee = prepare_fee::<N>(fee_record, fee)?;
+
+    // Proving cannot be interrupted once it has started, so check the signal right before it.
+    ensure_not_aborted(signal.as_ref())?;
 
     // Retrieve the private key.
-    let private_key = PrivateKey::from_str(&private_key)?;
-    // Generate the transfer transaction.
-    let execution = {
-        // Initialize an RNG.
-        let rng = &mut rand::thread_rng();
-
-        // Initialize the VM.
-        let store = ConsensusStore::<N, ConsensusMemory<N>>::open(None)?;
-        let vm = VM::from(store)?;
-
-        // Prepare the fees.
-        // let fee = match self.fee_record {
-        //     Some(record) => {
-        //         let record = Record::<N, Plaintext<N>>::from_str(&record)?;
-        //         let fee_amount = self.fee.unwrap_or(0);
-        //
-        //         Some((record, fee_amount))
-        //     }
-        //     None => None,
-        // };
-
-        // Prepare the inputs for a transfer.
-        let inputs = vec![
-            Value::Record(record.clone()),
-            Value::from_str(&format!("{}", recipient))?,
-            Value::from_str(&format!("{}u64", amount))?,
-        ];
-
-        // Create a new transaction.
-        Transaction::execute(
-            &vm,
-            &private_key,
-            ProgramID::from_str("credits.aleo")?,
-            Identifier::from_str("transfer")?,
-            inputs.iter(),
-            None,
-            Some(query),
-            rng,
-        )?
-    };
-    let locator = Locator::<N>::from_str("credits.aleo/transfer")?;
-    // Determine if the transaction should be broadcast, stored, or displayed to user.
-    handle_transaction(Some(broadcast), false, None, execution, locator.to_string()).await
+    let private_key = parse_private_key::<N>(&private_key)?;
+    // Generate the execution transaction.
+    let execution = build_execution(&private_key, &locator, &inputs, fee, &query_endpoint)?;
+    // Broadcast the transaction, or return it to the caller when no endpoint is given.
+    let build = broadcast.is_none();
+    handle_transaction(
+        broadcast,
+        build,
+        build,
+        execution,
+        locator.to_string(),
+        signal.as_ref(),
+    )
+    .await
+}
+
+/// Proves an execution of the function at the given locator.
+pub(crate) fn build_execution<N: Network>(
+    private_key: &PrivateKey<N>,
+    locator: &Locator<N>,
+    inputs: &[Value<N>],
+    fee: Option<(Record<N, Plaintext<N>>, u64)>,
+    query_endpoint: &str,
+) -> anyhow::Result<Transaction<N>> {
+    // Specify the query
+    let query = Query::from(query_endpoint);
+
+    // Initialize an RNG.
+    let rng = &mut rand::thread_rng();
+
+    // Retrieve the VM from the wallet context.
+    let context = wallet_context::<N>()?;
+    let vm = context.vm();
+
+    // Create a new transaction.
+    Ok(Transaction::execute(
+        vm,
+        private_key,
+        *locator.program_id(),
+        *locator.resource(),
+        inputs.iter(),
+        fee,
+        Some(query),
+        rng,
+    )
+    .map_err(|e| WalletError::Proving(e.to_string()))?)
+}
+
+/// Parses the optional fee record and checks that it holds enough microcredits to pay the fee.
+pub(crate) fn prepare_fee<N: Network>(
+    fee_record: Option<String>,
+    fee: Option<u64>,
+) -> anyhow::Result<Option<(Record<N, Plaintext<N>>, u64)>> {
+    match fee_record {
+        Some(record) => {
+            let record = parse_record::<N>(&record)?;
+            let fee_amount = fee.unwrap_or(0);
+
+            let balance = record_microcredits(&record);
+            ensure!(
+                balance >= fee_amount,
+                WalletError::InvalidRecord(format!(
+                    "The fee record holds {balance} microcredits, which cannot cover the fee of {fee_amount} microcredits"
+                ))
+            );
+
+            Ok(Some((record, fee_amount)))
+        }
+        None => {
+            ensure!(
+                fee.unwrap_or(0) == 0,
+                WalletError::InvalidInput(format!(
+                    "A fee record is required to pay a fee of {} microcredits",
+                    fee.unwrap_or(0)
+                ))
+            );
+            Ok(None)
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct TransactionOutput {
+    id: String,
+    transaction: Option<String>,
+    bytes: Option<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl TransactionOutput {
+    /// The transaction id.
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// The transaction as JSON, if it was displayed.
+    #[wasm_bindgen(getter)]
+    pub fn transaction(&self) -> Option<String> {
+        self.transaction.clone()
+    }
+
+    /// The little-endian transaction bytes, if they were stored.
+    #[wasm_bindgen(getter)]
+    pub fn bytes(&self) -> Option<Uint8Array> {
+        self.bytes.as_deref().map(Uint8Array::from)
+    }
+
+    /// The hex-encoded transaction bytes, if they were stored.
+    #[wasm_bindgen(getter)]
+    pub fn hex(&self) -> Option<String> {
+        self.bytes
+            .as_ref()
+            .map(|bytes| bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
 }
 
-async fn handle_transaction<N: Network>(
+pub(crate) async fn handle_transaction<N: Network>(
     broadcast: Option<String>,
     display: bool,
-    store: Option<String>,
+    store: bool,
     transaction: Transaction<N>,
     operation: String,
-) -> anyhow::Result<String> {
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<TransactionOutput> {
     // Get the transaction id.
     let transaction_id = transaction.id();
 
-    // Determine if the transaction should be stored.
-    if let Some(path) = store {
-        match PathBuf::from_str(&path) {
-            Ok(file_path) => {
-                let transaction_bytes = transaction.to_bytes_le()?;
-                std::fs::write(&file_path, transaction_bytes)?;
-                println!(
-                    "Transaction {transaction_id} was stored to {}",
-                    file_path.display()
-                );
-            }
-            Err(err) => {
-                println!("The transaction was unable to be stored due to: {err}");
-            }
-        }
+    let mut output = TransactionOutput {
+        id: transaction_id.to_string(),
+        ..Default::default()
     };
 
-    // Determine if the transaction should be broadcast or displayed to user.
+    // Determine if the transaction bytes should be returned to the caller.
+    if store {
+        output.bytes = Some(transaction.to_bytes_le()?);
+    }
+
+    // Determine if the transaction string should be returned to the caller.
+    if display {
+        output.transaction = Some(transaction.to_string());
+    }
+
+    // Determine if the transaction should be broadcast.
     if let Some(endpoint) = broadcast {
+        // Verify the transaction locally first, if requested.
+        if verify_transactions() {
+            let context = wallet_context::<N>()?;
+            ensure_verified(context.vm(), &transaction)?;
+        }
+
         // Send the deployment request to the local development node.
         let transaction_json = serde_json::to_value(&transaction)?;
-        match post_request(&endpoint, &transaction_json).await {
+        match post_request(&endpoint, &transaction_json, signal).await {
             Ok(response) => {
-                let response_text_future = response
-                    .text()
-                    .map_err(|js_value| anyhow::Error::msg(format!("{:?}", js_value)))?;
-                let response_text = JsFuture::from(response_text_future)
-                    .await
-                    .map_err(|js_value| anyhow::Error::msg(format!("{:?}", js_value)))?;
-                let response_text_str = response_text.as_string().unwrap_or_default();
-                let id: serde_json::Value = from_str(&response_text_str)?;
+                let response_text_str = response_text(response).await?;
+                let id: serde_json::Value = from_str(&response_text_str).map_err(|_| {
+                    WalletError::Parse(format!("Unexpected response: {response_text_str}"))
+                })?;
                 ensure!(
                     id == transaction_id.to_string(),
-                    "The response does not match the transaction id"
+                    WalletError::BroadcastMismatch {
+                        expected: transaction_id.to_string(),
+                        received: id.as_str().map(str::to_string).unwrap_or(id.to_string()),
+                    }
                 );
 
                 match transaction {
@@ -138,39 +476,33 @@ async fn handle_transaction<N: Network>(
                     }
                 }
             }
+            // An aborted broadcast is not a network failure.
+            Err(error) if matches!(error.downcast_ref(), Some(WalletError::Aborted)) => {
+                return Err(error)
+            }
             Err(error) => {
                 let error_message = format!("({})", error);
+                let status = match error.downcast_ref::<WalletError>() {
+                    Some(WalletError::Network { status, .. }) => *status,
+                    _ => None,
+                };
 
-                match transaction {
-                    Transaction::Deploy(..) => {
-                        bail!(
-                            "❌ Failed to deploy '{}' to {}: {}",
-                            operation,
-                            &endpoint,
-                            error_message
-                        )
-                    }
-                    Transaction::Execute(..) => {
-                        bail!(
-                            "❌ Failed to broadcast execution '{}' to {}: {}",
-                            operation,
-                            &endpoint,
-                            error_message
-                        )
-                    }
-                }
+                let message = match transaction {
+                    Transaction::Deploy(..) => format!(
+                        "❌ Failed to deploy '{}' to {}: {}",
+                        operation, &endpoint, error_message
+                    ),
+                    Transaction::Execute(..) => format!(
+                        "❌ Failed to broadcast execution '{}' to {}: {}",
+                        operation, &endpoint, error_message
+                    ),
+                };
+                bail!(WalletError::Network { status, message })
             }
         };
-
-        // Output the transaction id.
-        Ok(transaction_id.to_string())
-    } else if display {
-        // Output the transaction string.
-        Ok(transaction.to_string())
-    } else {
-        // TODO (raychu86): Handle the case where the user does not specify a broadcast or display flag.
-        Ok("".to_string())
     }
+
+    Ok(output)
 }
 
 // wasm-pack test --chrome
@@ -200,12 +532,15 @@ mod tests {
             conf[3].clone(),
             u64::from_str(&conf[4]).unwrap(),
             conf[5].clone(),
+            None,
+            None,
             conf[1].clone(),
-            conf[2].clone(),
+            Some(conf[2].clone()),
+            None,
         )
         .await
         .unwrap();
-        console_log!("{}", msg)
+        console_log!("{}", msg.id())
     }
 
     #[test]
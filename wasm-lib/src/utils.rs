@@ -1,14 +1,19 @@
+use crate::error::WalletError;
+use crate::transfer::CREDITS_PROVING_KEYS_T;
 use indexmap::IndexMap;
 use snarkvm_algorithms::snark::marlin::{CircuitProvingKey, MarlinHidingMode};
-use snarkvm_console_account::{PrivateKey, ViewKey};
+use snarkvm_console_account::{Address, PrivateKey, ViewKey};
 use snarkvm_console_network_environment::Environment;
-use snarkvm_console_program::Network;
+use snarkvm_console_program::{Network, Plaintext, Record};
 use snarkvm_utilities::FromBytes;
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::str::FromStr;
 use std::sync::Arc;
 use wasm_bindgen::{JsCast, JsValue};
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Headers, Request, RequestInit, Response};
+use web_sys::{AbortSignal, Headers, Request, RequestInit, Response};
 
 // pub fn set_panic_hook() {
 //     // When the `console_error_panic_hook` feature is enabled, we can call the
@@ -20,66 +25,134 @@ use web_sys::{Headers, Request, RequestInit, Response};
 //     #[cfg(feature = "console_error_panic_hook")]
 //     console_error_panic_hook::set_once();
 // }
-type MarlinProvingKey<N> = CircuitProvingKey<<N as Environment>::PairingCurve, MarlinHidingMode>;
+pub(crate) type MarlinProvingKey<N> =
+    CircuitProvingKey<<N as Environment>::PairingCurve, MarlinHidingMode>;
+
+pub(crate) type CreditsProvingKeys<N> = IndexMap<String, Arc<MarlinProvingKey<N>>>;
+
+thread_local! {
+    // The deserialized `credits.aleo` proving keys, kept for the lifetime of the wasm instance.
+    static CREDITS_PROVING_KEYS_CACHE: RefCell<Option<Rc<dyn Any>>> = RefCell::new(None);
+}
 
 pub(crate) fn parse_account<N: Network>(
     private_key: Option<String>,
     view_key: String,
 ) -> anyhow::Result<(Option<PrivateKey<N>>, ViewKey<N>)> {
-    let mut pk = None;
-    if let Some(..) = private_key {
-        let result = PrivateKey::<N>::from_str(&private_key.unwrap());
-        if let Ok(..) = result {
-            pk = Some(result.unwrap())
-        }
-    }
+    let pk = private_key
+        .map(|private_key| parse_private_key::<N>(&private_key))
+        .transpose()?;
 
-    let view_key = ViewKey::from_str(&view_key)?;
+    let view_key = ViewKey::from_str(&view_key)
+        .map_err(|e| WalletError::InvalidKey(format!("view key: {e}")))?;
     Ok((pk, view_key))
 }
 
+pub(crate) fn parse_private_key<N: Network>(private_key: &str) -> anyhow::Result<PrivateKey<N>> {
+    Ok(PrivateKey::<N>::from_str(private_key)
+        .map_err(|e| WalletError::InvalidKey(format!("private key: {e}")))?)
+}
+
+pub(crate) fn parse_address<N: Network>(address: &str) -> anyhow::Result<Address<N>> {
+    Ok(Address::<N>::from_str(address)
+        .map_err(|e| WalletError::InvalidKey(format!("address: {e}")))?)
+}
+
+pub(crate) fn parse_record<N: Network>(record: &str) -> anyhow::Result<Record<N, Plaintext<N>>> {
+    Ok(Record::<N, Plaintext<N>>::from_str(record)
+        .map_err(|e| WalletError::InvalidRecord(e.to_string()))?)
+}
+
+/// Returns the number of microcredits held by the given record.
+pub(crate) fn record_microcredits<N: Network>(record: &Record<N, Plaintext<N>>) -> u64 {
+    **record.gates()
+}
+
 pub(crate) async fn post_request(
     endpoint: &str,
     value: &serde_json::Value,
+    signal: Option<&AbortSignal>,
 ) -> anyhow::Result<Response> {
-    let window = web_sys::window().unwrap();
+    ensure_not_aborted(signal)?;
+
+    let window =
+        web_sys::window().ok_or_else(|| network_error("failed to load window".to_string()))?;
     let mut request_init = RequestInit::new();
     request_init.method("POST");
     request_init.mode(web_sys::RequestMode::Cors);
+    request_init.signal(signal);
 
     let headers =
-        Headers::new().map_err(|js_value| anyhow::Error::msg(format!("{:?}", js_value)))?;
+        Headers::new().map_err(|js_value| network_error(format!("{:?}", js_value)))?;
     headers
         .append("Content-Type", "application/json")
-        .map_err(|js_value| anyhow::Error::msg(format!("{:?}", js_value)))?;
+        .map_err(|js_value| network_error(format!("{:?}", js_value)))?;
     request_init.headers(&headers.into());
 
     let body = JsValue::from_str(&value.to_string());
     request_init.body(Some(&body));
 
     let request = Request::new_with_str_and_init(endpoint, &request_init)
-        .map_err(|js_value| anyhow::Error::msg(format!("{:?}", js_value)))?;
+        .map_err(|js_value| network_error(format!("{:?}", js_value)))?;
 
     let response = JsFuture::from(window.fetch_with_request(&request))
         .await
-        .map_err(|js_value| anyhow::Error::msg(format!("{:?}", js_value)))?;
-    let response = response.dyn_into::<Response>().unwrap();
+        .map_err(|js_value| fetch_error(signal, format!("{:?}", js_value)))?;
+    let response = response
+        .dyn_into::<Response>()
+        .map_err(|js_value| network_error(format!("{:?}", js_value)))?;
 
     if response.ok() {
         Ok(response)
     } else {
-        Err(anyhow::Error::msg("Error in response"))
+        let status = response.status();
+        let message = response_text(response).await.unwrap_or_default();
+        Err(WalletError::Network {
+            status: Some(status),
+            message,
+        }
+        .into())
     }
 }
 
-pub(crate) async fn get_request(endpoint: &str) -> anyhow::Result<Response> {
+/// A request that failed before the endpoint could answer it.
+fn network_error(message: String) -> WalletError {
+    WalletError::Network {
+        status: None,
+        message,
+    }
+}
+
+/// A fetch that was rejected, either because it was aborted or because the request failed.
+fn fetch_error(signal: Option<&AbortSignal>, message: String) -> WalletError {
+    match signal {
+        Some(signal) if signal.aborted() => WalletError::Aborted,
+        _ => network_error(message),
+    }
+}
+
+/// Fails with `WalletError::Aborted` once the given signal has been aborted.
+pub(crate) fn ensure_not_aborted(signal: Option<&AbortSignal>) -> anyhow::Result<()> {
+    match signal {
+        Some(signal) if signal.aborted() => Err(WalletError::Aborted.into()),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) async fn get_request(
+    endpoint: &str,
+    signal: Option<&AbortSignal>,
+) -> anyhow::Result<Response> {
+    ensure_not_aborted(signal)?;
+
     let mut opts = RequestInit::new();
     opts.method("GET");
+    opts.signal(signal);
 
     let request = match Request::new_with_str_and_init(endpoint, &opts) {
         Ok(req) => req,
         Err(e) => {
-            return Err(anyhow::Error::msg(e.as_string().unwrap_or_default()));
+            return Err(network_error(e.as_string().unwrap_or_default()).into());
         }
     };
 
@@ -88,26 +161,67 @@ pub(crate) async fn get_request(endpoint: &str) -> anyhow::Result<Response> {
             let resp_value = match JsFuture::from(window.fetch_with_request(&request)).await {
                 Ok(res_v) => res_v,
                 Err(e) => {
-                    return Err(anyhow::Error::msg(e.as_string().unwrap_or_default()));
+                    return Err(fetch_error(signal, e.as_string().unwrap_or_default()).into());
                 }
             };
             match resp_value.dyn_into() {
                 Ok(res) => Ok(res),
-                Err(e) => Err(anyhow::Error::msg(e.as_string().unwrap_or_default())),
+                Err(e) => Err(network_error(e.as_string().unwrap_or_default()).into()),
             }
         }
-        None => Err(anyhow::Error::msg("failed to load window")),
+        None => Err(network_error("failed to load window".to_string()).into()),
     }
 }
 
-// 验证邮箱
+/// Resolves after the given number of milliseconds.
+pub(crate) async fn sleep(millis: i32) -> anyhow::Result<()> {
+    let window =
+        web_sys::window().ok_or_else(|| network_error("failed to load window".to_string()))?;
+    let mut result = Ok(0);
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        result = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, millis);
+    });
+    result.map_err(|js_value| WalletError::Internal(format!("{:?}", js_value)))?;
+    JsFuture::from(promise)
+        .await
+        .map_err(|js_value| WalletError::Internal(format!("{:?}", js_value)))?;
+    Ok(())
+}
+
+/// Reads the body of the response as a string.
+pub(crate) async fn response_text(resp: Response) -> anyhow::Result<String> {
+    let resp = resp
+        .text()
+        .map_err(|err| network_error(err.as_string().unwrap_or_default()))?;
+    let resp_text = JsFuture::from(resp)
+        .await
+        .map_err(|err| network_error(err.as_string().unwrap_or_default()))?;
+    Ok(resp_text
+        .as_string()
+        .ok_or_else(|| WalletError::Parse("failed to convert resp_test to string".to_string()))?)
+}
+
+/// Returns the bundled `credits.aleo` proving keys, deserializing them on first use only.
+pub(crate) fn load_credits_proving_keys<N: Network>() -> anyhow::Result<Rc<CreditsProvingKeys<N>>> {
+    CREDITS_PROVING_KEYS_CACHE.with(|cache| {
+        if let Some(keys) = cache.borrow().as_ref() {
+            if let Ok(keys) = keys.clone().downcast::<CreditsProvingKeys<N>>() {
+                return Ok(keys);
+            }
+        }
+
+        let keys = Rc::new(get_credits_proving_keys::<N>(CREDITS_PROVING_KEYS_T)?);
+        *cache.borrow_mut() = Some(keys.clone());
+        Ok(keys)
+    })
+}
 
-fn get_credits_proving_keys<E: Environment>(data: &[u8]) -> anyhow::Result<IndexMap<String, Arc<MarlinProvingKey<E>>>> {
-    let credits_proving_keys_raw: IndexMap<String, Vec<u8>> = bincode::deserialize(data).map_err(|err| anyhow::Error::msg(format!("failed to deserialize data: {}", err)))?;
+pub(crate) fn get_credits_proving_keys<E: Environment>(data: &[u8]) -> anyhow::Result<IndexMap<String, Arc<MarlinProvingKey<E>>>> {
+    let credits_proving_keys_raw: IndexMap<String, Vec<u8>> = bincode::deserialize(data).map_err(|err| WalletError::Parse(format!("failed to deserialize data: {}", err)))?;
     let mut credits_proving_keys = IndexMap::new();
     for (k, v) in credits_proving_keys_raw.iter() {
         let le: Arc<MarlinProvingKey<E>> =
-            Arc::new(MarlinProvingKey::<E>::read_le(v.as_slice()).map_err(|err|anyhow::Error::msg(format!("failed to read_le data: {}", err)))?);
+            Arc::new(MarlinProvingKey::<E>::read_le(v.as_slice()).map_err(|err|WalletError::Parse(format!("failed to read_le data: {}", err)))?);
         credits_proving_keys.insert(k.clone(), le);
     }
     Ok(credits_proving_keys)